@@ -1,59 +1,922 @@
-use itertools::Itertools;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 
-#[derive(Clone, Debug)]
-pub enum Instr {
-    Nop(isize),
-    Acc(isize),
-    Jmp(isize)
+use lazy_static::lazy_static;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instr {
+    pub opcode: &'static str,
+    pub args: Vec<isize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program(pub Vec<Instr>);
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode)?;
+        for arg in &self.args {
+            write!(f, " {:+}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instr in self.iter() {
+            writeln!(f, "{}", instr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for Program {
+    type Target = Vec<Instr>;
+
+    fn deref(&self) -> &Vec<Instr> {
+        &self.0
+    }
+}
+
+impl DerefMut for Program {
+    fn deref_mut(&mut self) -> &mut Vec<Instr> {
+        &mut self.0
+    }
 }
 
-type State = isize;
+impl FromIterator<Instr> for Program {
+    fn from_iter<I: IntoIterator<Item = Instr>>(iter: I) -> Self {
+        Program(iter.into_iter().collect())
+    }
+}
+
+// Index of the accumulator in the machine registers
+pub const ACC: usize = 0;
+
+// How an instruction moves the instruction pointer, as far as it's known
+// without running it. Programs can only be analyzed when every flow is
+// static, and a static flow must agree with what `execute` returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    // Go to the following instruction
+    Next,
+    // Jump by the offset given in the argument at this position
+    Relative(usize),
+    // Depends on the registers, like a conditional jump
+    Dynamic,
+}
+
+// What an instruction does to the accumulator, as far as it's known without
+// running it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    // Leaves it alone
+    None,
+    // Adds the argument at this position
+    AddArg(usize),
+    // Anything else, like multiplying or setting it
+    Other,
+}
 
-pub type Program = Vec<Instr>;
+// Updates the registers and returns how far to move the instruction pointer
+pub type Execute = fn(&mut [isize], &[isize]) -> isize;
 
-pub fn parse_instruction(raw_instr: &str) -> Instr {
-    let (opcode, arg) = raw_instr.split_whitespace().collect_tuple().unwrap();
-    match opcode {
-        "nop" => Instr::Nop(arg.parse().expect("Invalid arg")),
-        "acc" => Instr::Acc(arg.parse().expect("Invalid arg")),
-        "jmp" => Instr::Jmp(arg.parse().expect("Invalid arg")),
-        instr => panic!(format!("Unimplemented instruction {}", instr))
+#[derive(Clone, Copy)]
+pub struct Opcode {
+    pub name: &'static str,
+    pub arity: usize,
+    pub execute: Execute,
+    pub flow: Flow,
+    pub effect: Effect,
+}
+
+// Table of the opcodes a machine understands
+#[derive(Clone)]
+pub struct InstructionSet {
+    pub n_registers: usize,
+    opcodes: HashMap<&'static str, Opcode>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingOpcode,
+    UnknownOpcode(String),
+    WrongArity { expected: usize, found: usize },
+    InvalidArgument(String),
+    InvalidLabel(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    // 1-based, like editors show them
+    pub line: usize,
+    pub text: String,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} ({:?}): ", self.line, self.text)?;
+        match &self.kind {
+            ParseErrorKind::MissingOpcode => write!(f, "missing opcode"),
+            ParseErrorKind::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+            ParseErrorKind::WrongArity { expected, found } => {
+                write!(f, "expected {} arguments, found {}", expected, found)
+            }
+            ParseErrorKind::InvalidArgument(arg) => write!(f, "invalid argument {}", arg),
+            ParseErrorKind::InvalidLabel(label) => write!(f, "invalid label {:?}", label),
+            ParseErrorKind::UndefinedLabel(label) => write!(f, "undefined label {}", label),
+            ParseErrorKind::DuplicateLabel(label) => write!(f, "label {} defined twice", label),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl InstructionSet {
+    pub fn new(n_registers: usize) -> Self {
+        Self {
+            n_registers,
+            opcodes: HashMap::new(),
+        }
+    }
+
+    // The handheld game console: a single accumulator and nop/acc/jmp
+    pub fn handheld() -> Self {
+        let mut isa = Self::new(1);
+        isa.register("nop", 1, |_, _| 1, Flow::Next, Effect::None)
+            .register(
+                "acc",
+                1,
+                |registers, args| {
+                    registers[ACC] += args[0];
+                    1
+                },
+                Flow::Next,
+                Effect::AddArg(0),
+            )
+            .register("jmp", 1, |_, args| args[0], Flow::Relative(0), Effect::None);
+        isa
+    }
+
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        arity: usize,
+        execute: Execute,
+        flow: Flow,
+        effect: Effect,
+    ) -> &mut Self {
+        self.opcodes.insert(
+            name,
+            Opcode {
+                name,
+                arity,
+                execute,
+                flow,
+                effect,
+            },
+        );
+        self
+    }
+
+    pub fn opcode(&self, name: &str) -> Option<&Opcode> {
+        self.opcodes.get(name)
+    }
+
+    pub fn parse_instruction(&self, raw_instr: &str) -> Result<Instr, ParseErrorKind> {
+        self.assemble(raw_instr, 0, &HashMap::new())
+    }
+
+    // Arguments can be numbers or labels, which are turned into the offset
+    // from the instruction at `index` to the labelled one
+    fn assemble(
+        &self,
+        text: &str,
+        index: usize,
+        labels: &HashMap<&str, usize>,
+    ) -> Result<Instr, ParseErrorKind> {
+        let mut tokens = text.split_whitespace();
+        let name = tokens.next().ok_or(ParseErrorKind::MissingOpcode)?;
+        let opcode = self
+            .opcode(name)
+            .ok_or_else(|| ParseErrorKind::UnknownOpcode(name.to_owned()))?;
+        let args = tokens
+            .map(|arg| match (arg.parse(), labels.get(arg)) {
+                (Ok(n), _) => Ok(n),
+                (Err(_), Some(&target)) => Ok(target as isize - index as isize),
+                (Err(_), None) if is_label(arg) => {
+                    Err(ParseErrorKind::UndefinedLabel(arg.to_owned()))
+                }
+                (Err(_), None) => Err(ParseErrorKind::InvalidArgument(arg.to_owned())),
+            })
+            .collect::<Result<Vec<isize>, _>>()?;
+        if args.len() != opcode.arity {
+            return Err(ParseErrorKind::WrongArity {
+                expected: opcode.arity,
+                found: args.len(),
+            });
+        }
+        Ok(Instr {
+            opcode: opcode.name,
+            args,
+        })
+    }
+
+    // Source lines look like `loop: jmp -3 ; comment`, where both the label
+    // and the comment are optional. A label alone on its line refers to the
+    // next instruction, or to the end of the program after the last one.
+    pub fn parse_program(&self, source: &str) -> Result<Program, ParseError> {
+        let mut labels: HashMap<&str, usize> = HashMap::new();
+        let mut instructions: Vec<(usize, &str, &str)> = vec![];
+        for (i, line) in source.lines().enumerate() {
+            let error = |kind| ParseError {
+                line: i + 1,
+                text: line.to_owned(),
+                kind,
+            };
+            let code = line.split(';').next().unwrap_or("");
+            let code = match code.find(':') {
+                Some(colon) => {
+                    let label = code[..colon].trim();
+                    if !is_label(label) {
+                        return Err(error(ParseErrorKind::InvalidLabel(label.to_owned())));
+                    }
+                    if labels.insert(label, instructions.len()).is_some() {
+                        return Err(error(ParseErrorKind::DuplicateLabel(label.to_owned())));
+                    }
+                    &code[colon + 1..]
+                }
+                None => code,
+            };
+            if !code.trim().is_empty() {
+                instructions.push((i + 1, line, code));
+            }
+        }
+        instructions
+            .into_iter()
+            .enumerate()
+            .map(|(index, (line_number, line, code))| {
+                self.assemble(code, index, &labels)
+                    .map_err(|kind| ParseError {
+                        line: line_number,
+                        text: line.to_owned(),
+                        kind,
+                    })
+            })
+            .collect()
+    }
+
+    // Like the plain listing, but every jump target gets a label and jumps
+    // refer to it by name. Parsing the result gives back the same program.
+    pub fn disassemble(&self, program: &Program) -> String {
+        let flow = |instr: &Instr| self.opcode(instr.opcode).map(|opcode| opcode.flow);
+        // Index of the labelled instruction each jump goes to, if any
+        let targets: Vec<Option<usize>> = program
+            .iter()
+            .enumerate()
+            .map(|(i, instr)| match flow(instr) {
+                Some(Flow::Relative(arg)) => {
+                    let target = i as isize + instr.args[arg];
+                    if target >= 0 && target as usize <= program.len() {
+                        Some(target as usize)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect();
+        let mut labelled = vec![false; program.len() + 1];
+        for &target in targets.iter().flatten() {
+            labelled[target] = true;
+        }
+        let mut listing = String::new();
+        for (i, instr) in program.iter().enumerate() {
+            if labelled[i] {
+                listing += &format!("l{}:\n", i);
+            }
+            listing += &format!("    {}", instr.opcode);
+            for (a, arg) in instr.args.iter().enumerate() {
+                match targets[i] {
+                    Some(target) if flow(instr) == Some(Flow::Relative(a)) => {
+                        listing += &format!(" l{}", target)
+                    }
+                    _ => listing += &format!(" {:+}", arg),
+                }
+            }
+            listing.push('\n');
+        }
+        if labelled[program.len()] {
+            listing += &format!("l{}:\n", program.len());
+        }
+        listing
+    }
+}
+
+fn is_label(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+lazy_static! {
+    pub static ref HANDHELD: InstructionSet = InstructionSet::handheld();
+}
+
+pub fn parse_instruction(raw_instr: &str) -> Result<Instr, ParseErrorKind> {
+    HANDHELD.parse_instruction(raw_instr)
+}
+
+pub fn parse_program(source: &str) -> Result<Program, ParseError> {
+    HANDHELD.parse_program(source)
+}
+
+// How a run of a program ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    // Reached the instruction right after the last one
+    Terminated { acc: isize },
+    // Was about to execute instruction `at` for the second time
+    Looped { acc: isize, at: usize },
+    // Jumped anywhere else outside the program
+    JumpedOutOfBounds { target: isize },
+}
+
+pub struct Machine<'a> {
+    isa: &'a InstructionSet,
+    program: &'a Program,
+    pub ip: isize,
+    pub registers: Vec<isize>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(isa: &'a InstructionSet, program: &'a Program) -> Self {
+        Self {
+            isa,
+            program,
+            ip: 0,
+            registers: vec![0; isa.n_registers],
+        }
+    }
+
+    pub fn acc(&self) -> isize {
+        self.registers[ACC]
+    }
+
+    pub fn program(&self) -> &'a Program {
+        self.program
+    }
+
+    pub fn current(&self) -> Option<&'a Instr> {
+        usize::try_from(self.ip)
+            .ok()
+            .and_then(|ip| self.program.get(ip))
+    }
+
+    // Executes the instruction under the instruction pointer and returns its
+    // index, or None if the pointer is outside the program.
+    pub fn step(&mut self) -> Option<usize> {
+        let instr = self.current()?;
+        let executed = self.ip as usize;
+        let opcode = self
+            .isa
+            .opcode(instr.opcode)
+            .expect("Program uses an opcode missing from the instruction set");
+        self.ip += (opcode.execute)(&mut self.registers, &instr.args);
+        Some(executed)
+    }
+
+    // Runs until the program leaves its bounds or an instruction is about to
+    // be executed twice
+    pub fn run(&mut self) -> Outcome {
+        let mut visited = vec![false; self.program.len()];
+        loop {
+            match self.current() {
+                None if self.ip == self.program.len() as isize => {
+                    return Outcome::Terminated { acc: self.acc() }
+                }
+                None => return Outcome::JumpedOutOfBounds { target: self.ip },
+                Some(_) if visited[self.ip as usize] => {
+                    return Outcome::Looped {
+                        acc: self.acc(),
+                        at: self.ip as usize,
+                    }
+                }
+                Some(_) => {
+                    let executed = self.step().unwrap();
+                    visited[executed] = true;
+                }
+            }
+        }
     }
 }
 
-pub fn parse_program(source: &str) -> Program {
-    source.lines().map(parse_instruction).collect()
+// Replaces the opcode of a single instruction, keeping its arguments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mutation {
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+pub const NOP_JMP: [Mutation; 2] = [
+    Mutation {
+        from: "nop",
+        to: "jmp",
+    },
+    Mutation {
+        from: "jmp",
+        to: "nop",
+    },
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repair {
+    pub index: usize,
+    pub program: Program,
+    pub acc: isize,
+}
+
+pub fn repair(
+    program: &Program,
+    mutations: &[Mutation],
+) -> Result<Vec<Repair>, analysis::AnalysisError> {
+    repair_with(&HANDHELD, program, mutations)
+}
+
+// Finds every single-instruction mutation that makes a looping program
+// terminate. Only instructions on the looping path can matter, and changing
+// one makes the program terminate exactly when its new successor already
+// terminated in the original program: the path from there can't go back
+// through the changed instruction, or that one would terminate too. For the
+// same reason the final accumulator is what the original path gathers up to
+// the changed instruction, plus its new effect, plus what the original
+// program gathers from the new successor to the end, so no repair is run.
+// Programs that already terminate need no repair, and mutations must keep
+// the number of arguments.
+pub fn repair_with(
+    isa: &InstructionSet,
+    program: &Program,
+    mutations: &[Mutation],
+) -> Result<Vec<Repair>, analysis::AnalysisError> {
+    for &mutation in mutations {
+        match (isa.opcode(mutation.from), isa.opcode(mutation.to)) {
+            (Some(from), Some(to)) if from.arity == to.arity => {}
+            _ => return Err(analysis::AnalysisError::InvalidMutation(mutation)),
+        }
+    }
+    let cfg = analysis::ControlFlowGraph::new(isa, program)?;
+    let acc_to_end = cfg.acc_to_end();
+    let acc_from = |target: isize| match usize::try_from(target) {
+        Ok(target) if target == program.len() => Some(0),
+        Ok(target) => acc_to_end.get(target).copied().flatten(),
+        Err(_) => None,
+    };
+    if acc_from(0).is_some() {
+        return Ok(vec![]);
+    }
+    let mut repairs = vec![];
+    let mut acc_before = 0;
+    for index in cfg.path_from(0) {
+        for mutation in mutations {
+            if program[index].opcode != mutation.from {
+                continue;
+            }
+            let patched = Instr {
+                opcode: mutation.to,
+                args: program[index].args.clone(),
+            };
+            let successor = index as isize + analysis::offset(isa, &patched)?;
+            if let Some(acc_after) = acc_from(successor) {
+                let mut program = program.clone();
+                let acc = acc_before + analysis::acc_delta(isa, &patched)? + acc_after;
+                program[index] = patched;
+                repairs.push(Repair {
+                    index,
+                    program,
+                    acc,
+                });
+            }
+        }
+        acc_before += cfg.acc_delta(index);
+    }
+    Ok(repairs)
 }
 
 pub mod interpreter {
     use std::iter;
 
-    use super::Instr;
-    use super::Program;
-    use super::State;
+    use super::{Instr, Machine, Outcome, Program, HANDHELD};
 
-    pub fn step_through(program: &Program, start: usize) -> impl Iterator<Item = (State, Instr, usize, isize)> + '_ {
+    pub fn run(program: &Program) -> Outcome {
+        Machine::new(&HANDHELD, program).run()
+    }
+
+    // Runs the program from `start`, yielding the accumulator, the executed
+    // instruction, its index and the next instruction pointer after every
+    // step. Stops before executing any instruction a second time.
+    pub fn step_through(
+        program: &Program,
+        start: usize,
+    ) -> impl Iterator<Item = (isize, Instr, usize, isize)> + '_ {
         let mut visited: Vec<bool> = vec![false; program.len()];
-        let mut iptr: isize = start as isize;
-        let mut state: State = 0;
+        let mut machine = Machine::new(&HANDHELD, program);
+        machine.ip = start as isize;
         iter::from_fn(move || {
-            if iptr < 0 || iptr as usize >= program.len() { return None; }
-            if visited[iptr as usize] { return None; }
-            let prev = iptr as usize;
+            let instr = machine.current()?;
+            if visited[machine.ip as usize] {
+                return None;
+            }
+            let prev = machine.step()?;
             visited[prev] = true;
-            match program[prev] {
-                Instr::Nop(_n) => {
-                    iptr += 1;
-                },
-                Instr::Acc(n) => {
-                    state += n;
-                    iptr += 1;
-                },
-                Instr::Jmp(n) => {
-                    iptr += n;
+            Some((machine.acc(), instr.to_owned(), prev, machine.ip))
+        })
+    }
+}
+
+pub mod analysis {
+    use std::collections::VecDeque;
+    use std::fmt;
+
+    use super::{Effect, Flow, Instr, InstructionSet, Mutation, Opcode, Program};
+
+    // Why a program can't be analyzed without running it
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum AnalysisError {
+        UnknownOpcode(&'static str),
+        DynamicFlow(&'static str),
+        UnknownEffect(&'static str),
+        InvalidMutation(Mutation),
+    }
+
+    impl fmt::Display for AnalysisError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AnalysisError::UnknownOpcode(name) => {
+                    write!(f, "opcode {} is not in the instruction set", name)
+                }
+                AnalysisError::DynamicFlow(name) => {
+                    write!(f, "where {} jumps depends on the registers", name)
+                }
+                AnalysisError::UnknownEffect(name) => {
+                    write!(f, "{} doesn't just add to the accumulator", name)
+                }
+                AnalysisError::InvalidMutation(Mutation { from, to }) => {
+                    write!(f, "cannot turn {} into {}", from, to)
                 }
             }
-            Some((state, program[prev].to_owned(), prev, iptr))
-        })
+        }
+    }
+
+    impl std::error::Error for AnalysisError {}
+
+    fn opcode<'a>(isa: &'a InstructionSet, instr: &Instr) -> Result<&'a Opcode, AnalysisError> {
+        isa.opcode(instr.opcode)
+            .ok_or(AnalysisError::UnknownOpcode(instr.opcode))
+    }
+
+    // How far the instruction moves the instruction pointer
+    pub fn offset(isa: &InstructionSet, instr: &Instr) -> Result<isize, AnalysisError> {
+        match opcode(isa, instr)?.flow {
+            Flow::Next => Ok(1),
+            Flow::Relative(arg) => Ok(instr.args[arg]),
+            Flow::Dynamic => Err(AnalysisError::DynamicFlow(instr.opcode)),
+        }
+    }
+
+    // How much the instruction adds to the accumulator
+    pub fn acc_delta(isa: &InstructionSet, instr: &Instr) -> Result<isize, AnalysisError> {
+        match opcode(isa, instr)?.effect {
+            Effect::None => Ok(0),
+            Effect::AddArg(arg) => Ok(instr.args[arg]),
+            Effect::Other => Err(AnalysisError::UnknownEffect(instr.opcode)),
+        }
+    }
+
+    // Every instruction has exactly one successor, since jumps are relative
+    // to constant offsets: programs with dynamic flows are rejected, and so
+    // are those changing the accumulator other than by constants. Node
+    // `program.len()` stands for termination.
+    pub struct ControlFlowGraph {
+        successors: Vec<isize>,
+        predecessors: Vec<Vec<usize>>,
+        acc_deltas: Vec<isize>,
+    }
+
+    impl ControlFlowGraph {
+        pub fn new(isa: &InstructionSet, program: &Program) -> Result<Self, AnalysisError> {
+            let successors: Vec<isize> = program
+                .iter()
+                .enumerate()
+                .map(|(i, instr)| Ok(i as isize + offset(isa, instr)?))
+                .collect::<Result<_, _>>()?;
+            let mut predecessors = vec![vec![]; program.len() + 1];
+            for (i, &next) in successors.iter().enumerate() {
+                if next >= 0 && next as usize <= program.len() {
+                    predecessors[next as usize].push(i);
+                }
+            }
+            Ok(Self {
+                successors,
+                predecessors,
+                acc_deltas: program
+                    .iter()
+                    .map(|instr| acc_delta(isa, instr))
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+
+        pub fn len(&self) -> usize {
+            self.successors.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.successors.is_empty()
+        }
+
+        pub fn successor(&self, index: usize) -> isize {
+            self.successors[index]
+        }
+
+        pub fn predecessors(&self, index: usize) -> &[usize] {
+            &self.predecessors[index]
+        }
+
+        pub fn acc_delta(&self, index: usize) -> isize {
+            self.acc_deltas[index]
+        }
+
+        // Instructions executed starting from `start`, until the program
+        // leaves its bounds or loops back
+        pub fn path_from(&self, start: usize) -> Vec<usize> {
+            let mut visited = vec![false; self.len()];
+            let mut path = vec![];
+            let mut current = start as isize;
+            while current >= 0 && (current as usize) < self.len() && !visited[current as usize] {
+                visited[current as usize] = true;
+                path.push(current as usize);
+                current = self.successor(current as usize);
+            }
+            path
+        }
+
+        // For every instruction, whether execution starting from it terminates.
+        pub fn terminating(&self) -> Vec<bool> {
+            self.acc_to_end().iter().map(Option::is_some).collect()
+        }
+
+        // For every instruction from which execution terminates, how much
+        // the accumulator grows from there to the end. Walks the graph
+        // backwards from the termination node, so it's linear in the size of
+        // the program.
+        pub fn acc_to_end(&self) -> Vec<Option<isize>> {
+            let mut acc = vec![None; self.len() + 1];
+            acc[self.len()] = Some(0);
+            let mut queue = VecDeque::from(vec![self.len()]);
+            while let Some(node) = queue.pop_front() {
+                let acc_after = acc[node].unwrap();
+                for &prev in self.predecessors(node) {
+                    if acc[prev].is_none() {
+                        acc[prev] = Some(self.acc_delta(prev) + acc_after);
+                        queue.push_back(prev);
+                    }
+                }
+            }
+            acc.pop();
+            acc
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY8: &str = include_str!("../inputs/day8.in");
+    const DAY8_EXAMPLE: &str = include_str!("../inputs/day8-example.in");
+
+    #[test]
+    fn test_listing_round_trip() {
+        let program = parse_program(DAY8).unwrap();
+        assert_eq!(program.to_string(), DAY8);
+        assert_eq!(parse_program(&program.to_string()).unwrap(), program);
+    }
+
+    #[test]
+    fn test_labelled_round_trip() {
+        let program = parse_program(DAY8).unwrap();
+        let listing = HANDHELD.disassemble(&program);
+        assert_eq!(parse_program(&listing).unwrap(), program);
+    }
+
+    #[test]
+    fn test_labels_and_comments() {
+        let source = "\
+            ; counts down from three
+            start: acc +3
+            loop:
+                acc -1 ; decrement
+                jmp end
+                jmp loop
+            end:";
+        let program = parse_program(source).unwrap();
+        assert_eq!(program.to_string(), "acc +3\nacc -1\njmp +2\njmp -2\n");
+        let error = parse_program("jmp nowhere").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::UndefinedLabel("nowhere".to_owned())
+        );
+        let error = parse_program("nop +0\n1x: nop +0").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.kind, ParseErrorKind::InvalidLabel("1x".to_owned()));
+        assert_eq!(
+            error.to_string(),
+            "line 2 (\"1x: nop +0\"): invalid label \"1x\""
+        );
+        let error = parse_program(": nop +0").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidLabel("".to_owned()));
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let looping = parse_program(DAY8_EXAMPLE).unwrap();
+        assert_eq!(
+            interpreter::run(&looping),
+            Outcome::Looped { acc: 5, at: 1 }
+        );
+        let terminating = parse_program("acc +2\njmp +2\nacc +40\nacc -1").unwrap();
+        assert_eq!(
+            interpreter::run(&terminating),
+            Outcome::Terminated { acc: 1 }
+        );
+        let escaping = parse_program("acc +1\njmp -5").unwrap();
+        assert_eq!(
+            interpreter::run(&escaping),
+            Outcome::JumpedOutOfBounds { target: -4 }
+        );
+        let escaping = parse_program("jmp -5").unwrap();
+        assert_eq!(
+            interpreter::run(&escaping),
+            Outcome::JumpedOutOfBounds { target: -5 }
+        );
+    }
+
+    #[test]
+    fn test_control_flow_graph() {
+        let looping = parse_program(DAY8_EXAMPLE).unwrap();
+        let cfg = analysis::ControlFlowGraph::new(&HANDHELD, &looping).unwrap();
+        assert_eq!(cfg.path_from(0), vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(cfg.predecessors(1), &[0, 4]);
+        // Only the last instruction falls through to the end
+        assert_eq!(
+            cfg.terminating(),
+            vec![false, false, false, false, false, false, false, false, true]
+        );
+
+        let terminating = parse_program("acc +2\njmp +2\nacc +40\nacc -1").unwrap();
+        let cfg = analysis::ControlFlowGraph::new(&HANDHELD, &terminating).unwrap();
+        assert_eq!(cfg.path_from(0), vec![0, 1, 3]);
+        assert_eq!(cfg.terminating(), vec![true; 4]);
+
+        // Jumping out of bounds neither loops nor terminates
+        let escaping = parse_program("nop +0\njmp -5").unwrap();
+        let cfg = analysis::ControlFlowGraph::new(&HANDHELD, &escaping).unwrap();
+        assert_eq!(cfg.successor(1), -4);
+        assert_eq!(cfg.path_from(0), vec![0, 1]);
+        assert_eq!(cfg.terminating(), vec![false, false]);
+    }
+
+    fn brute_force_repairs(program: &Program) -> Vec<(usize, isize)> {
+        (0..program.len())
+            .flat_map(|index| NOP_JMP.iter().map(move |mutation| (index, mutation)))
+            .filter(|(index, mutation)| program[*index].opcode == mutation.from)
+            .filter_map(|(index, mutation)| {
+                let mut patched = program.clone();
+                patched[index].opcode = mutation.to;
+                match interpreter::run(&patched) {
+                    Outcome::Terminated { acc } => Some((index, acc)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    fn repairs(program: &Program) -> Vec<(usize, isize)> {
+        repair(program, &NOP_JMP)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.index, r.acc))
+            .collect()
+    }
+
+    #[test]
+    fn test_repair() {
+        let example = parse_program(DAY8_EXAMPLE).unwrap();
+        assert_eq!(repairs(&example), vec![(7, 8)]);
+        let repaired = &repair(&example, &NOP_JMP).unwrap()[0];
+        assert_eq!(
+            interpreter::run(&repaired.program),
+            Outcome::Terminated { acc: 8 }
+        );
+
+        let terminating = parse_program("acc +2\njmp +2\nacc +40\nacc -1").unwrap();
+        assert!(repairs(&terminating).is_empty());
+    }
+
+    #[test]
+    fn test_repair_matches_brute_force() {
+        let program = parse_program(DAY8).unwrap();
+        assert_eq!(repairs(&program), vec![(253, 797)]);
+        assert_eq!(repairs(&program), brute_force_repairs(&program));
+        let example = parse_program(DAY8_EXAMPLE).unwrap();
+        assert_eq!(repairs(&example), brute_force_repairs(&example));
+    }
+
+    #[test]
+    fn test_dynamic_flow() {
+        // Counts the accumulator down to zero
+        let mut isa = InstructionSet::new(1);
+        isa.register(
+            "set",
+            1,
+            |registers, args| {
+                registers[ACC] = args[0];
+                1
+            },
+            Flow::Next,
+            Effect::Other,
+        )
+        .register(
+            "dec",
+            0,
+            |registers, _| {
+                registers[ACC] -= 1;
+                1
+            },
+            Flow::Next,
+            Effect::Other,
+        )
+        .register(
+            "jnz",
+            1,
+            |registers, args| if registers[ACC] != 0 { args[0] } else { 1 },
+            Flow::Dynamic,
+            Effect::None,
+        );
+        let program = isa.parse_program("set +5\nloop: dec\njnz loop").unwrap();
+        let mut machine = Machine::new(&isa, &program);
+        let steps = std::iter::from_fn(|| machine.step()).count();
+        assert_eq!(steps, 11);
+        assert_eq!((machine.ip, machine.acc()), (3, 0));
+        assert_eq!(
+            analysis::ControlFlowGraph::new(&isa, &program).err(),
+            Some(analysis::AnalysisError::DynamicFlow("jnz"))
+        );
+        assert!(repair_with(&isa, &program, &NOP_JMP).is_err());
+    }
+
+    #[test]
+    fn test_unanalyzable_repairs() {
+        let mut isa = InstructionSet::handheld();
+        isa.register(
+            "mul",
+            1,
+            |registers, args| {
+                registers[ACC] *= args[0];
+                1
+            },
+            Flow::Next,
+            Effect::Other,
+        )
+        .register("pass", 0, |_, _| 1, Flow::Next, Effect::None);
+        let program = isa.parse_program("acc +2\nmul +3\njmp -2").unwrap();
+        assert_eq!(
+            repair_with(&isa, &program, &NOP_JMP),
+            Err(analysis::AnalysisError::UnknownEffect("mul"))
+        );
+
+        let program = parse_program(DAY8_EXAMPLE).unwrap();
+        for mutation in [
+            Mutation {
+                from: "jmp",
+                to: "pass",
+            },
+            Mutation {
+                from: "nop",
+                to: "halt",
+            },
+        ] {
+            assert_eq!(
+                repair_with(&isa, &program, &[mutation]),
+                Err(analysis::AnalysisError::InvalidMutation(mutation))
+            );
+        }
     }
 }
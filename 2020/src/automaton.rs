@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+// How a cell evolves, from its state and how many of its neighbours are live
+pub trait Rule {
+    type State: Copy + Eq + Hash;
+
+    fn is_live(&self, state: Self::State) -> bool;
+    fn next(&self, state: Self::State, live_neighbours: usize) -> Self::State;
+}
+
+// The neighbours of every cell of a width x height grid, by index in the
+// row-major cells. Computed once, since they don't depend on the states.
+pub struct Topology {
+    width: usize,
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    // The (up to) 8 surrounding cells
+    pub fn adjacent(width: usize, height: usize) -> Self {
+        Self::looking(width, height, Some(1), |_, _| true)
+    }
+
+    // The first cell in each of the 8 directions for which `is_visible` holds
+    pub fn line_of_sight<F>(width: usize, height: usize, is_visible: F) -> Self
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        Self::looking(width, height, None, is_visible)
+    }
+
+    fn looking<F>(width: usize, height: usize, range: Option<usize>, is_visible: F) -> Self
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let in_bounds =
+            |r: isize, c: isize| r >= 0 && (r as usize) < height && c >= 0 && (c as usize) < width;
+        let neighbours = (0..height * width)
+            .map(|i| {
+                let (r, c) = ((i / width) as isize, (i % width) as isize);
+                DIRECTIONS
+                    .iter()
+                    .filter_map(|&(dr, dc)| {
+                        (1..)
+                            .take(range.unwrap_or(usize::MAX))
+                            .map(|n| (r + dr * n, c + dc * n))
+                            .take_while(|&(r, c)| in_bounds(r, c))
+                            .map(|(r, c)| (r as usize, c as usize))
+                            .find(|&(r, c)| is_visible(r, c))
+                            .map(|(r, c)| r * width + c)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { width, neighbours }
+    }
+
+    pub fn neighbours(&self, index: usize) -> &[usize] {
+        &self.neighbours[index]
+    }
+}
+
+// How a run ended: either nothing changes anymore, or the states loop through
+// `period` generations starting at `start`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Fate {
+    Stable { generation: usize },
+    Cycle { start: usize, period: usize },
+}
+
+pub struct Automaton<R: Rule> {
+    rule: R,
+    topology: Topology,
+    cells: Vec<R::State>,
+    // Where the next generation is written, swapped with `cells` after
+    next: Vec<R::State>,
+    generation: usize,
+}
+
+impl<R: Rule> Automaton<R> {
+    pub fn new(rule: R, topology: Topology, cells: Vec<R::State>) -> Self {
+        assert_eq!(
+            cells.len(),
+            topology.neighbours.len(),
+            "Cells don't fit the topology"
+        );
+        Self {
+            rule,
+            topology,
+            next: cells.clone(),
+            cells,
+            generation: 0,
+        }
+    }
+
+    pub fn cells(&self) -> &[R::State] {
+        &self.cells
+    }
+
+    // A grid with no columns has no cells either, so no rows
+    pub fn rows(&self) -> impl Iterator<Item = &[R::State]> {
+        self.cells.chunks(self.topology.width.max(1))
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn count(&self, state: R::State) -> usize {
+        self.cells.iter().filter(|&&s| s == state).count()
+    }
+
+    // Advances one generation, returning how many cells changed. A
+    // generation that changes nothing isn't counted.
+    pub fn step(&mut self) -> usize {
+        let Self {
+            rule,
+            topology,
+            cells,
+            next,
+            ..
+        } = self;
+        let mut changed = 0;
+        for (i, next) in next.iter_mut().enumerate() {
+            let live = topology
+                .neighbours(i)
+                .iter()
+                .filter(|&&n| rule.is_live(cells[n]))
+                .count();
+            *next = rule.next(cells[i], live);
+            if *next != cells[i] {
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            std::mem::swap(&mut self.cells, &mut self.next);
+            self.generation += 1;
+        }
+        changed
+    }
+
+    // Every new generation, until one is the same as the previous or as any
+    // earlier one, so oscillating rules end too
+    pub fn generations(&mut self) -> impl Iterator<Item = Vec<R::State>> + '_ {
+        let mut seen = HashSet::new();
+        seen.insert(self.cells.clone());
+        std::iter::from_fn(move || match self.step() {
+            0 => None,
+            _ if seen.insert(self.cells.clone()) => Some(self.cells.clone()),
+            _ => None,
+        })
+    }
+
+    // Runs until nothing changes or the states repeat, remembering all the
+    // generations seen
+    pub fn settle(&mut self) -> Fate {
+        self.settle_with(|_| {})
+    }
+
+    // Same as `settle`, calling `visit` on every new generation along the way
+    pub fn settle_with<F: FnMut(&Self)>(&mut self, mut visit: F) -> Fate {
+        let mut seen = HashMap::new();
+        loop {
+            if let Some(start) = seen.insert(self.cells.clone(), self.generation) {
+                let period = self.generation - start;
+                return Fate::Cycle { start, period };
+            }
+            if self.step() == 0 {
+                return Fate::Stable {
+                    generation: self.generation,
+                };
+            }
+            visit(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Life;
+
+    impl Rule for Life {
+        type State = bool;
+
+        fn is_live(&self, state: bool) -> bool {
+            state
+        }
+
+        fn next(&self, state: bool, live_neighbours: usize) -> bool {
+            matches!((state, live_neighbours), (true, 2) | (_, 3))
+        }
+    }
+
+    fn life(rows: &[&str]) -> Automaton<Life> {
+        let cells = rows
+            .iter()
+            .flat_map(|r| r.chars().map(|c| c == '#'))
+            .collect();
+        Automaton::new(Life, Topology::adjacent(rows[0].len(), rows.len()), cells)
+    }
+
+    #[test]
+    fn test_still_life_and_oscillator() {
+        let mut block = life(&["....", ".##.", ".##.", "...."]);
+        assert_eq!(block.settle(), Fate::Stable { generation: 0 });
+
+        let mut blinker = life(&[".....", "..#..", "..#..", "..#..", "....."]);
+        assert_eq!(
+            blinker.settle(),
+            Fate::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(blinker.count(true), 3);
+    }
+
+    #[test]
+    fn test_generations_end() {
+        let mut blinker = life(&[".....", "..#..", "..#..", "..#..", "....."]);
+        assert_eq!(blinker.generations().count(), 1);
+
+        let mut block = life(&["....", ".##.", ".##.", "...."]);
+        assert_eq!(block.generations().count(), 0);
+    }
+
+    #[test]
+    fn test_settle_with() {
+        let mut blinker = life(&[".....", "..#..", "..#..", "..#..", "....."]);
+        let mut visited = vec![];
+        let fate = blinker.settle_with(|automaton| visited.push(automaton.generation()));
+        assert_eq!(
+            fate,
+            Fate::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(visited, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_empty_grid() {
+        let mut empty = Automaton::new(Life, Topology::adjacent(0, 3), vec![]);
+        assert_eq!(empty.rows().count(), 0);
+        assert_eq!(empty.settle(), Fate::Stable { generation: 0 });
+        let empty = Automaton::new(Life, Topology::adjacent(3, 0), vec![]);
+        assert_eq!(empty.rows().count(), 0);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let walls = ["#..#", "....", "..#."];
+        let is_wall = |r: usize, c: usize| walls[r].as_bytes()[c] == b'#';
+        let topology = Topology::line_of_sight(4, 3, is_wall);
+        assert_eq!(topology.neighbours(0), &[3, 10]);
+        assert_eq!(Topology::adjacent(4, 3).neighbours(0), &[1, 5, 4]);
+    }
+}
@@ -1,80 +1,142 @@
+use itertools::Itertools;
+use lazy_static::lazy_static;
 use regex::Regex;
+use std::fmt;
 use std::io::{self, BufRead};
+use std::str::FromStr;
 
-type Rule = (char, (usize, usize));
+lazy_static! {
+    static ref POLICY_LINE: Regex = Regex::new(r"^(\d+)-(\d+) (\w): (\w+)$").unwrap();
+}
+
+// A line of the password database: the numbers are read differently by
+// each policy
+#[derive(Debug, PartialEq, Eq)]
+struct PolicyLine {
+    first: usize,
+    second: usize,
+    letter: char,
+    password: String,
+}
+
+impl FromStr for PolicyLine {
+    type Err = String;
 
-fn check_valid_password(rule: Rule, password: &str) -> bool {
-    let (required_char, (min, max)) = rule;
-    match password.matches(required_char).count() {
-        c if c >= min && c <= max => true,
-        _ => false,
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let captures = POLICY_LINE
+            .captures(line)
+            .ok_or_else(|| format!("invalid line {}", line))?;
+        let number = |i: usize| {
+            captures[i]
+                .parse()
+                .map_err(|_| format!("number too big in {}", line))
+        };
+        Ok(PolicyLine {
+            first: number(1)?,
+            second: number(2)?,
+            letter: captures[3].chars().next().unwrap(),
+            password: captures[4].to_owned(),
+        })
     }
 }
 
-fn check_valid_password_positions(rule: Rule, password: &str) -> bool {
-    let (required_char, (min, max)) = rule;
-    let bytestring = password.as_bytes();
-    match (
-        required_char,
-        bytestring[min - 1] as char,
-        bytestring[max - 1] as char,
-    ) {
-        (wanted, c1, c2) if (c1 == wanted) ^ (c2 == wanted) => true,
-        _ => false,
+impl fmt::Display for PolicyLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.first, self.second, self.letter, self.password
+        )
     }
 }
 
-fn parse_line<'a>(password_line: &'a str) -> Result<(Rule, &'a str), &'static str> {
-    let re = Regex::new(r"^(\d+)-(\d+) (\w): (\w+)$").unwrap();
-    match re.captures(password_line) {
-        Some(captures) => {
-            let start = captures
-                .get(1)
-                .map(|s| s.as_str().parse::<usize>().unwrap())
-                .unwrap();
-            let end = captures
-                .get(2)
-                .map(|s| s.as_str().parse::<usize>().unwrap())
-                .unwrap();
-            Ok((
-                (
-                    captures.get(3).unwrap().as_str().chars().next().unwrap(),
-                    (start, end),
-                ),
-                captures.get(4).unwrap().as_str(),
-            ))
-        }
-        None => Err("Invalid line"),
+trait PasswordPolicy {
+    fn name(&self) -> &'static str;
+    fn is_valid(&self, line: &PolicyLine) -> bool;
+}
+
+// The letter must appear between `first` and `second` times
+struct CountPolicy;
+
+// The letter must be at exactly one of the 1-based positions
+struct PositionPolicy;
+
+impl PasswordPolicy for CountPolicy {
+    fn name(&self) -> &'static str {
+        "count"
+    }
+
+    fn is_valid(&self, line: &PolicyLine) -> bool {
+        let count = line.password.matches(line.letter).count();
+        (line.first..=line.second).contains(&count)
     }
 }
 
-fn count_valid_passwords<P>(password_checker: P) -> usize
-where
-    P: Fn(Rule, &str) -> bool,
-{
+impl PasswordPolicy for PositionPolicy {
+    fn name(&self) -> &'static str {
+        "positions"
+    }
+
+    fn is_valid(&self, line: &PolicyLine) -> bool {
+        let has_letter = |position: usize| {
+            position
+                .checked_sub(1)
+                .and_then(|i| line.password.chars().nth(i))
+                == Some(line.letter)
+        };
+        has_letter(line.first) ^ has_letter(line.second)
+    }
+}
+
+const POLICIES: [&dyn PasswordPolicy; 2] = [&CountPolicy, &PositionPolicy];
+
+fn read_lines() -> Vec<PolicyLine> {
     io::stdin()
         .lock()
         .lines()
-        .filter(|l| {
-            let line = l.as_ref().expect("Could not read line").as_str();
-            let (rule, password) = parse_line(&line).expect("invalid line");
-            password_checker(rule, password)
+        .enumerate()
+        .map(|(i, l)| {
+            l.expect("Could not read line")
+                .parse()
+                .unwrap_or_else(|e| panic!("line {}: {}", i + 1, e))
         })
-        .count()
+        .collect()
+}
+
+// Lists the lines that fail at least one policy, and which ones they fail
+fn print_report(lines: &[PolicyLine]) {
+    for (i, line) in lines.iter().enumerate() {
+        let failed = POLICIES
+            .iter()
+            .filter(|policy| !policy.is_valid(line))
+            .map(|policy| policy.name())
+            .join(", ");
+        if !failed.is_empty() {
+            println!("line {} ({}) fails {}", i + 1, line, failed);
+        }
+    }
+}
+
+fn count_valid_passwords(policy: &dyn PasswordPolicy) -> usize {
+    let lines = read_lines();
+    if std::env::args().any(|arg| arg == "--report") {
+        print_report(&lines);
+    }
+    lines.iter().filter(|line| policy.is_valid(line)).count()
 }
 
 fn part1() {
-    let valid_count = count_valid_passwords(check_valid_password);
+    let valid_count = count_valid_passwords(&CountPolicy);
     println!("{} valid passwords", valid_count);
 }
 
 fn part2() {
-    let valid_count = count_valid_passwords(check_valid_password_positions);
+    let valid_count = count_valid_passwords(&PositionPolicy);
     println!("{} valid passwords", valid_count);
 }
 
 pub fn main() {
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(),
         Some(p) if p == "part2" => part2(),
         _ => println!("Please specify a part (part1 | part2)"),
@@ -1,54 +1,178 @@
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::fmt;
 use std::io::{self, BufRead};
+use std::str::FromStr;
 
-fn flat_index(width: usize, row: usize, col: usize) -> usize {
-    row * width + col
+// The map, which repeats itself to the right (and to the left) forever
+struct TreeMap {
+    width: usize,
+    trees: Vec<Vec<bool>>,
 }
 
-fn count_trees(flat_matrix: &Vec<char>, width: usize, row_step: usize, col_step: usize) -> usize {
-    let mut r = 0;
-    let mut c = 0;
-    let mut trees_count = 0;
-    while flat_index(width, r, c) < flat_matrix.len() {
-        if flat_matrix[flat_index(width, r, c)] == '#' { trees_count = trees_count + 1; }
-        r = r + row_step;
-        c = (c + col_step) % width;
+// `right` columns every `down` rows, with `down` positive. Right 2 down 2
+// stops on every second row, so it's not the same slope as right 1 down 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Slope {
+    right: i64,
+    down: i64,
+}
+
+impl Slope {
+    fn new(right: i64, down: i64) -> Result<Self, String> {
+        if down <= 0 {
+            return Err(format!("the toboggan can only go down, not {}", down));
+        }
+        Ok(Slope { right, down })
     }
-    trees_count
 }
 
-fn read_treemap() -> ((usize, usize), Vec<char>) {
-    let stdin = io::stdin();
-    let mut lines = stdin.lock().lines().peekable();
-    let width = lines.peek()
-        .unwrap()
-        .as_ref()
-        .unwrap()
-        .len();
-    let matrix: Vec<char> = lines
-        .map(|l| l.expect("Could not read line"))
-        .flat_map(|l| l.chars().collect::<Vec<char>>())
-        .collect::<Vec<_>>();
-    ((width, matrix.len() / width), matrix)
+// Written as right/down, e.g. 3/1 or 1/2
+impl FromStr for Slope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/').map(|n| n.parse::<i64>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(right)), Some(Ok(down))) => Slope::new(right, down),
+            _ => Err(format!("invalid slope {}, expected right/down", s)),
+        }
+    }
+}
+
+impl fmt::Display for Slope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "right {}, down {}", self.right, self.down)
+    }
 }
 
+impl TreeMap {
+    fn height(&self) -> usize {
+        self.trees.len()
+    }
+
+    fn is_tree(&self, row: usize, col: i64) -> bool {
+        self.trees[row][col.rem_euclid(self.width as i64) as usize]
+    }
+
+    // Positions the toboggan stops at, from the top left to the bottom
+    fn path(&self, slope: Slope) -> impl Iterator<Item = (usize, i64)> {
+        (0..self.height())
+            .step_by(slope.down as usize)
+            .enumerate()
+            .map(move |(step, row)| (row, step as i64 * slope.right))
+    }
+
+    fn count_trees(&self, slope: Slope) -> usize {
+        self.path(slope)
+            .filter(|&(row, col)| self.is_tree(row, col))
+            .count()
+    }
+
+    // The map with the path drawn on it, folded back into a single tile:
+    // O where the toboggan stops on open ground, X where it hits a tree
+    fn render(&self, slope: Slope) -> String {
+        let stops: HashSet<(usize, usize)> = self
+            .path(slope)
+            .map(|(row, col)| (row, col.rem_euclid(self.width as i64) as usize))
+            .collect();
+        self.trees
+            .iter()
+            .enumerate()
+            .map(|(row, line)| {
+                line.iter()
+                    .enumerate()
+                    .map(|(col, &tree)| match (stops.contains(&(row, col)), tree) {
+                        (true, true) => 'X',
+                        (true, false) => 'O',
+                        (false, true) => '#',
+                        (false, false) => '.',
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+}
+
+// Every slope going at most `bound` columns either way and at most `bound`
+// rows down per stop
+fn slopes_up_to(bound: i64) -> Vec<Slope> {
+    (1..=bound)
+        .cartesian_product(-bound..=bound)
+        .map(|(down, right)| Slope { right, down })
+        .collect()
+}
+
+fn read_treemap() -> TreeMap {
+    let trees: Vec<Vec<bool>> = io::stdin()
+        .lock()
+        .lines()
+        .map(|l| {
+            l.expect("Could not read line")
+                .chars()
+                .map(|c| c == '#')
+                .collect()
+        })
+        .collect();
+    let width = trees.first().map_or(0, |row| row.len());
+    assert!(
+        width > 0 && trees.iter().all(|row| row.len() == width),
+        "The map must be a non-empty rectangle"
+    );
+    TreeMap { width, trees }
+}
+
+// The value following `flag` on the command line, if any
+fn flag_value<T: FromStr>(flag: &str) -> Option<T>
+where
+    T::Err: fmt::Display,
+{
+    std::env::args()
+        .skip_while(|arg| arg != flag)
+        .nth(1)
+        .map(|v| {
+            v.parse()
+                .unwrap_or_else(|e| panic!("Invalid {} {}: {}", flag, v, e))
+        })
+}
+
+// The slope can be changed with `--slope right/down`, and `--render` draws
+// the path taken
 fn part1() {
-    let ((width, _height), treemap) = read_treemap();
-    let trees = count_trees(&treemap, width, 1, 3);
-    println!("{} trees", trees);
+    let treemap = read_treemap();
+    let slope = flag_value("--slope").unwrap_or(Slope { right: 3, down: 1 });
+    if std::env::args().any(|arg| arg == "--render") {
+        println!("{}", treemap.render(slope));
+    }
+    println!("{} trees", treemap.count_trees(slope));
 }
 
+// With `--search N`, also look for the slopes up to N hitting the fewest
+// and the most trees
 fn part2() {
-    let ((width, _height), treemap) = read_treemap();
-    let slopes = vec![(1, 1), (1, 3), (1, 5), (1, 7), (2, 1)];
-    let mut res = 1;
-    for (row_step, col_step) in slopes {
-        res = res * count_trees(&treemap, width, row_step, col_step)
-    }
+    let treemap = read_treemap();
+    let slopes = ["1/1", "3/1", "5/1", "7/1", "1/2"];
+    let res: usize = slopes
+        .iter()
+        .map(|s| treemap.count_trees(s.parse().unwrap()))
+        .product();
     println!("{} trees", res);
+    if let Some(bound) = flag_value::<i64>("--search") {
+        let counts = slopes_up_to(bound)
+            .into_iter()
+            .map(|slope| (treemap.count_trees(slope), slope))
+            .collect_vec();
+        let fewest = counts.iter().min_by_key(|(count, _)| count);
+        let most = counts.iter().max_by_key(|(count, _)| count);
+        if let (Some((min, best)), Some((max, worst))) = (fewest, most) {
+            println!("Fewest trees: {} going {}", min, best);
+            println!("Most trees: {} going {}", max, worst);
+        }
+    }
 }
 
 pub fn main() {
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(),
         Some(p) if p == "part2" => part2(),
         _ => println!("Please specify a part (part1 | part2)"),
@@ -1,96 +1,380 @@
 use regex::Regex;
-use lazy_static::lazy_static;
-use std::{collections::HashSet, ops::RangeBounds};
+use std::collections::HashSet;
 use std::io::{self, Read};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
-fn has_required_fields(passport: &str) -> bool {
-    let required_fields: HashSet<&str> = vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"]
-        .into_iter()
-        .collect();
-    passport
-        .split_whitespace()
-        .map(|field| field.splitn(2, ':').next().expect("Invalid field"))
-        .collect::<HashSet<&str>>()
-        .is_superset(&required_fields)
-}
-
-fn validate_year<R: RangeBounds<usize>>(range: R, year_string: &str, ) -> bool {
-    match year_string.parse::<usize>() {
-        Ok(year) if range.contains(&year) => true,
-        _ => false
-    }
-}
-
-fn validate_height(hgt: &str) -> bool {
-    lazy_static! {
-        static ref HGT_RE: Regex = Regex::new(r"^(\d+)(cm|in)$").unwrap();
-    }
-    match HGT_RE.captures(hgt) {
-        None => false,
-        Some(captures) => {
-            let amount: usize = captures.get(1).unwrap().as_str().parse().unwrap();
-            let unit: &str = captures.get(2).unwrap().as_str();
-            match (amount, unit) {
-                (150..=193, "cm") => true,
-                (59..=76, "in") => true,
-                _ => false
+// One rule per line: field name, `required` or `optional`, then the
+// validator and its parameters. Lines starting with `#` are comments.
+const DEFAULT_SCHEMA: &str = r"
+byr required year 1920-2002
+iyr required year 2010-2020
+eyr required year 2020-2030
+hgt required units cm:150-193 in:59-76
+hcl required regex ^#[0-9a-f]{6}$
+ecl required oneof amb blu brn gry grn hzl oth
+pid required regex ^\d{9}$
+cid optional any
+";
+
+enum Validator {
+    Any,
+    Year(RangeInclusive<usize>),
+    Units(Vec<(String, RangeInclusive<usize>)>),
+    Regex(Regex),
+    OneOf(HashSet<String>),
+}
+
+struct FieldRule {
+    name: String,
+    required: bool,
+    validator: Validator,
+}
+
+struct Schema {
+    rules: Vec<FieldRule>,
+}
+
+#[derive(Debug, PartialEq)]
+enum FieldStatus {
+    Valid,
+    Missing,
+    Invalid(String),
+    Unknown,
+}
+
+struct PassportReport {
+    fields: Vec<(String, FieldStatus)>,
+}
+
+fn parse_range(range: &str) -> Result<RangeInclusive<usize>, String> {
+    let mut bounds = range.splitn(2, '-').map(|n| n.parse::<usize>());
+    match (bounds.next(), bounds.next()) {
+        (Some(Ok(min)), Some(Ok(max))) => Ok(min..=max),
+        _ => Err(format!("invalid range {}", range)),
+    }
+}
+
+impl Validator {
+    fn parse(kind: &str, params: &[&str]) -> Result<Self, String> {
+        match (kind, params) {
+            ("any", []) => Ok(Validator::Any),
+            ("year", [range]) => Ok(Validator::Year(parse_range(range)?)),
+            ("units", units) if !units.is_empty() => units
+                .iter()
+                .map(|unit| {
+                    let mut it = unit.splitn(2, ':');
+                    match (it.next(), it.next()) {
+                        (Some(name), Some(range)) => Ok((name.to_owned(), parse_range(range)?)),
+                        _ => Err(format!("invalid unit range {}", unit)),
+                    }
+                })
+                .collect::<Result<_, _>>()
+                .map(Validator::Units),
+            ("regex", [re]) => Regex::new(re)
+                .map(Validator::Regex)
+                .map_err(|e| e.to_string()),
+            ("oneof", values) if !values.is_empty() => Ok(Validator::OneOf(
+                values.iter().map(|v| v.to_string()).collect(),
+            )),
+            _ => Err(format!("invalid validator {} {}", kind, params.join(" "))),
+        }
+    }
+
+    // None if the value is valid, otherwise the reason why it isn't
+    fn check(&self, value: &str) -> Option<String> {
+        match self {
+            Validator::Any => None,
+            Validator::Year(range) => match value.parse::<usize>() {
+                Ok(year) if value.len() == 4 && range.contains(&year) => None,
+                _ => Some(format!("not a year in {:?}", range)),
+            },
+            Validator::Units(units) => {
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (amount, unit) = value.split_at(split);
+                match (
+                    amount.parse::<usize>(),
+                    units.iter().find(|(u, _)| u == unit),
+                ) {
+                    (Ok(amount), Some((_, range))) if range.contains(&amount) => None,
+                    (Ok(_), Some((unit, range))) => Some(format!("{} not in {:?}", unit, range)),
+                    _ => Some("not an amount with a known unit".to_owned()),
+                }
             }
+            Validator::Regex(re) if re.is_match(value) => None,
+            Validator::Regex(re) => Some(format!("doesn't match {}", re)),
+            Validator::OneOf(values) if values.contains(value) => None,
+            Validator::OneOf(_) => Some("not one of the allowed values".to_owned()),
         }
     }
 }
 
-fn validate_field(fkey: &str, fvalue: &str) -> bool {
-    lazy_static! {
-        static ref HCL_RE: Regex = Regex::new(r"^#[0-9a-f]{6,6}$").unwrap();
-        static ref ECL_RE: Regex = Regex::new(r"^(amb)|(blu)|(brn)|(gry)|(grn)|(hzl)|(oth)$").unwrap();
-        static ref PID_RE: Regex = Regex::new(r"^\d{9,9}$").unwrap();
+impl FromStr for FieldRule {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [name, presence, kind, ref params @ ..] => {
+                let required = match presence {
+                    "required" => true,
+                    "optional" => false,
+                    _ => return Err(format!("invalid presence {}", presence)),
+                };
+                Ok(FieldRule {
+                    name: name.to_owned(),
+                    required,
+                    validator: Validator::parse(kind, params)?,
+                })
+            }
+            _ => Err("expected name, presence and validator".to_owned()),
+        }
     }
-    match fkey {
-        "byr" => validate_year(1920..=2002, fvalue),
-        "iyr" => validate_year(2010..=2020, fvalue),
-        "eyr" => validate_year(2020..=2030, fvalue),
-        "hgt" => validate_height(fvalue),
-        "hcl" => HCL_RE.is_match(fvalue),
-        "ecl" => ECL_RE.is_match(fvalue),
-        "pid" => PID_RE.is_match(fvalue),
-        "cid" => true,
-        _ => false,
+}
+
+impl FromStr for Schema {
+    type Err = String;
+
+    fn from_str(config: &str) -> Result<Self, Self::Err> {
+        let rules = config
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_number, line)| {
+                line.parse()
+                    .map_err(|e| format!("schema line {}: {}", line_number, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Schema { rules })
+    }
+}
+
+impl Schema {
+    // Fields in the schema come first, in its order, then the unknown ones in
+    // the order they appear in the passport
+    fn validate(&self, passport: &str) -> PassportReport {
+        let values: Vec<(&str, &str)> = passport
+            .split_whitespace()
+            .map(|field| {
+                let mut it = field.splitn(2, ':');
+                (it.next().unwrap(), it.next().unwrap_or(""))
+            })
+            .collect();
+        let value = |name: &str| {
+            values
+                .iter()
+                .rev()
+                .find(|(key, _)| *key == name)
+                .map(|&(_, value)| value)
+        };
+        let mut fields: Vec<(String, FieldStatus)> = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                let status = match value(&rule.name) {
+                    None if rule.required => FieldStatus::Missing,
+                    None => return None,
+                    Some(value) => match rule.validator.check(value) {
+                        None => FieldStatus::Valid,
+                        Some(reason) => FieldStatus::Invalid(reason),
+                    },
+                };
+                Some((rule.name.clone(), status))
+            })
+            .collect();
+        fields.extend(
+            values
+                .iter()
+                .filter(|(key, _)| !self.rules.iter().any(|rule| rule.name == *key))
+                .map(|(key, _)| (key.to_string(), FieldStatus::Unknown)),
+        );
+        PassportReport { fields }
     }
 }
 
-fn has_valid_fields(passport: &str) -> bool {
-    passport
-        .split_whitespace()
-        .all(|field| {
-            let mut it = field.splitn(2, ':');
-            match (it.next(), it.next()) {
-                (Some(key), Some(value)) => validate_field(key, value),
-                _ => false
+impl PassportReport {
+    fn has_required_fields(&self) -> bool {
+        self.fields
+            .iter()
+            .all(|(_, status)| *status != FieldStatus::Missing)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.fields
+            .iter()
+            .all(|(_, status)| *status == FieldStatus::Valid)
+    }
+
+    fn print(&self, index: usize) {
+        let verdict = if self.is_valid() { "valid" } else { "invalid" };
+        println!("Passport {}: {}", index + 1, verdict);
+        for (name, status) in &self.fields {
+            match status {
+                FieldStatus::Valid => println!("  {} ok", name),
+                FieldStatus::Missing => println!("  {} missing", name),
+                FieldStatus::Invalid(reason) => println!("  {} invalid: {}", name, reason),
+                FieldStatus::Unknown => println!("  {} is not in the schema", name),
             }
-        })
+        }
+    }
+}
+
+// The schema can be loaded from a file passed after the part
+fn read_schema() -> Schema {
+    let config = match std::env::args().nth(2).filter(|arg| !arg.starts_with("--")) {
+        Some(path) => std::fs::read_to_string(path).expect("Could not read schema"),
+        None => DEFAULT_SCHEMA.to_owned(),
+    };
+    config
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid schema: {}", e))
 }
 
-fn count_valid_passports<V: Fn(&str) -> bool>(validator: V) -> usize {
+fn count_valid_passports<V: Fn(&PassportReport) -> bool>(validator: V) -> usize {
+    let schema = read_schema();
     let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer).expect("Could not read from stdin!");
-    let passports: Vec<&str> = buffer.split("\n\n").collect();
-    passports.iter().filter(|&p| validator(p)).count()
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .expect("Could not read from stdin!");
+    let reports: Vec<PassportReport> = buffer.split("\n\n").map(|p| schema.validate(p)).collect();
+    if std::env::args().any(|arg| arg == "--report") {
+        for (i, report) in reports.iter().enumerate() {
+            report.print(i);
+        }
+    }
+    reports.iter().filter(|&r| validator(r)).count()
 }
 
 fn part1() {
-    let valid_passports_count = count_valid_passports(has_required_fields);
+    let valid_passports_count = count_valid_passports(PassportReport::has_required_fields);
     println!("{} valid passports", valid_passports_count)
 }
 
 fn part2() {
-    let valid_passports_count = count_valid_passports(|p| has_required_fields(p) && has_valid_fields(p));
+    let valid_passports_count = count_valid_passports(PassportReport::is_valid);
     println!("{} valid passports", valid_passports_count)
 }
 
 pub fn main() {
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(),
         Some(p) if p == "part2" => part2(),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_schema() -> Schema {
+        DEFAULT_SCHEMA.parse().unwrap()
+    }
+
+    fn check(rule: &str, value: &str) -> Option<String> {
+        let schema: Schema = format!("field required {}", rule).parse().unwrap();
+        schema.rules[0].validator.check(value)
+    }
+
+    #[test]
+    fn test_example() {
+        let schema = default_schema();
+        let reports: Vec<PassportReport> = include_str!("../../inputs/day4-example.in")
+            .split("\n\n")
+            .map(|p| schema.validate(p))
+            .collect();
+        let complete: Vec<bool> = reports.iter().map(|r| r.has_required_fields()).collect();
+        assert_eq!(complete, vec![true, false, true, false]);
+        let valid: Vec<bool> = reports.iter().map(|r| r.is_valid()).collect();
+        assert_eq!(valid, vec![true, false, true, false]);
+        assert!(reports[1]
+            .fields
+            .contains(&("hgt".to_owned(), FieldStatus::Missing)));
+        // An optional field can be left out
+        assert!(reports[2].fields.iter().all(|(name, _)| name != "cid"));
+    }
+
+    #[test]
+    fn test_unknown_fields_in_input_order() {
+        let report = default_schema().validate("zzz:1 byr:1990 aaa:2\nmmm:3");
+        let unknown: Vec<&str> = report
+            .fields
+            .iter()
+            .filter(|(_, status)| *status == FieldStatus::Unknown)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(unknown, vec!["zzz", "aaa", "mmm"]);
+        assert_eq!(report.fields[0], ("byr".to_owned(), FieldStatus::Valid));
+    }
+
+    #[test]
+    fn test_validators() {
+        assert_eq!(check("any", ""), None);
+
+        assert_eq!(check("year 1920-2002", "2002"), None);
+        let not_a_year = Some("not a year in 1920..=2002".to_owned());
+        assert_eq!(check("year 1920-2002", "2003"), not_a_year);
+        assert_eq!(check("year 1920-2002", "02000"), not_a_year);
+        assert_eq!(check("year 1920-2002", "19x0"), not_a_year);
+
+        let height = "units cm:150-193 in:59-76";
+        assert_eq!(check(height, "150cm"), None);
+        assert_eq!(check(height, "76in"), None);
+        assert_eq!(
+            check(height, "194cm"),
+            Some("cm not in 150..=193".to_owned())
+        );
+        let unknown_unit = Some("not an amount with a known unit".to_owned());
+        assert_eq!(check(height, "190"), unknown_unit);
+        assert_eq!(check(height, "60ft"), unknown_unit);
+        assert_eq!(check(height, "cm"), unknown_unit);
+
+        assert_eq!(check("regex ^#[0-9a-f]{6}$", "#123abc"), None);
+        assert_eq!(
+            check("regex ^#[0-9a-f]{6}$", "#123abz"),
+            Some("doesn't match ^#[0-9a-f]{6}$".to_owned())
+        );
+
+        assert_eq!(check("oneof amb blu", "blu"), None);
+        assert_eq!(
+            check("oneof amb blu", "wat"),
+            Some("not one of the allowed values".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let error = |config: &str| config.parse::<Schema>().err();
+        assert_eq!(
+            error("byr sometimes year 1920-2002"),
+            Some("schema line 1: invalid presence sometimes".to_owned())
+        );
+        assert_eq!(
+            error("\n# a comment\nbyr required"),
+            Some("schema line 3: expected name, presence and validator".to_owned())
+        );
+        assert_eq!(
+            error("byr required year 1920"),
+            Some("schema line 1: invalid range 1920".to_owned())
+        );
+        assert_eq!(
+            error("hgt required units cm150"),
+            Some("schema line 1: invalid unit range cm150".to_owned())
+        );
+        assert_eq!(
+            error("ecl required oneof"),
+            Some("schema line 1: invalid validator oneof ".to_owned())
+        );
+        assert_eq!(
+            error("x required frob 1 2"),
+            Some("schema line 1: invalid validator frob 1 2".to_owned())
+        );
+        assert!(error("pid required regex (").is_some());
+        let schema: Schema = "# only comments\n\ncid optional any".parse().unwrap();
+        assert_eq!(schema.rules.len(), 1);
+        assert!(!schema.rules[0].required);
+    }
+}
@@ -1,57 +1,254 @@
-use std::io::{self, BufRead};
 use itertools::Itertools;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+// The row is binary-encoded in the first ROW_BITS letters (F = 0, B = 1),
+// the column in the last COLUMN_BITS (L = 0, R = 1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct BoardingPass<const ROW_BITS: u32 = 7, const COLUMN_BITS: u32 = 3> {
+    row: usize,
+    column: usize,
+}
 
-fn seat_id(seat: (usize, usize)) -> usize {
-    seat.0 * 8 + seat.1
+#[derive(Debug, PartialEq, Eq)]
+enum PassError {
+    Length { expected: usize, found: usize },
+    InvalidLetter { position: usize, letter: char },
 }
 
-fn decode_seat(pass_code: &str) -> (usize, usize) {
-    let (row, col) = pass_code.split_at(7);
-    let row = row.chars().fold(0, |acc, c| {
-        match c {
-            'F' => acc * 2,
-            'B' => acc * 2 + 1,
-            _ => panic!("Invalid boarding pass!")
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::Length { expected, found } => {
+                write!(f, "expected {} letters, found {}", expected, found)
+            }
+            PassError::InvalidLetter { position, letter } => {
+                write!(f, "invalid letter {} at position {}", letter, position + 1)
+            }
+        }
+    }
+}
+
+impl<const ROW_BITS: u32, const COLUMN_BITS: u32> BoardingPass<ROW_BITS, COLUMN_BITS> {
+    const ROWS: usize = 1 << ROW_BITS;
+    const COLUMNS: usize = 1 << COLUMN_BITS;
+
+    fn id(&self) -> usize {
+        self.row << COLUMN_BITS | self.column
+    }
+
+    fn from_id(id: usize) -> Option<Self> {
+        if id < Self::ROWS * Self::COLUMNS {
+            Some(Self {
+                row: id >> COLUMN_BITS,
+                column: id & (Self::COLUMNS - 1),
+            })
+        } else {
+            None
         }
-    });
-    let col = col.chars().fold(0, |acc, c| {
-        match c {
-            'L' => acc * 2,
-            'R' => acc * 2 + 1,
-            _ => panic!("Invalid boarding pass!")
+    }
+}
+
+impl<const ROW_BITS: u32, const COLUMN_BITS: u32> FromStr for BoardingPass<ROW_BITS, COLUMN_BITS> {
+    type Err = PassError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let expected = (ROW_BITS + COLUMN_BITS) as usize;
+        let found = code.chars().count();
+        if found != expected {
+            return Err(PassError::Length { expected, found });
         }
-    });
-    (row, col)
+        let id = code
+            .chars()
+            .enumerate()
+            .try_fold(0, |id, (position, letter)| {
+                let bit = match (letter, position < ROW_BITS as usize) {
+                    ('F', true) | ('L', false) => 0,
+                    ('B', true) | ('R', false) => 1,
+                    _ => return Err(PassError::InvalidLetter { position, letter }),
+                };
+                Ok(id << 1 | bit)
+            })?;
+        Ok(Self::from_id(id).unwrap())
+    }
 }
 
-fn part1() {
-    let max_seat_id = io::stdin()
+impl<const ROW_BITS: u32, const COLUMN_BITS: u32> fmt::Display
+    for BoardingPass<ROW_BITS, COLUMN_BITS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits = |value: usize, n: u32, letters: [char; 2]| -> String {
+            (0..n).rev().map(|i| letters[value >> i & 1]).collect()
+        };
+        write!(
+            f,
+            "{}{}",
+            bits(self.row, ROW_BITS, ['F', 'B']),
+            bits(self.column, COLUMN_BITS, ['L', 'R'])
+        )
+    }
+}
+
+// The free seats whose neighbouring ids are both taken
+fn missing_seats<const R: u32, const C: u32>(
+    passes: &[BoardingPass<R, C>],
+) -> Vec<BoardingPass<R, C>> {
+    passes
+        .iter()
+        .map(BoardingPass::id)
+        .sorted()
+        .tuple_windows()
+        .filter(|&(prev, next)| next == prev + 2)
+        .filter_map(|(prev, _)| BoardingPass::from_id(prev + 1))
+        .collect()
+}
+
+// One line per row: # for occupied seats, . for free ones, X for the missing
+// ones, with an aisle in the middle
+fn render_seat_map<const R: u32, const C: u32>(
+    passes: &[BoardingPass<R, C>],
+    missing: &[BoardingPass<R, C>],
+) -> String {
+    let occupied: HashSet<&BoardingPass<R, C>> = passes.iter().collect();
+    (0..BoardingPass::<R, C>::ROWS)
+        .map(|row| {
+            let seats: String = (0..BoardingPass::<R, C>::COLUMNS)
+                .map(|column| {
+                    let seat = BoardingPass { row, column };
+                    if occupied.contains(&seat) {
+                        '#'
+                    } else if missing.contains(&seat) {
+                        'X'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let (left, right) = seats.split_at(seats.len() / 2);
+            format!("{:>3} {} {}", row, left, right)
+        })
+        .join("\n")
+}
+
+fn read_passes() -> Vec<BoardingPass> {
+    io::stdin()
         .lock()
         .lines()
-        .map(|l| decode_seat(&l.unwrap()))
-        .map(seat_id)
+        .enumerate()
+        .map(|(i, l)| {
+            let code = l.expect("Could not read line");
+            code.parse().unwrap_or_else(|e| {
+                panic!("Invalid boarding pass {} on line {}: {}", code, i + 1, e)
+            })
+        })
+        .collect()
+}
+
+fn part1() {
+    let max_seat_id = read_passes()
+        .iter()
+        .map(BoardingPass::id)
         .max()
         .expect("No boarding passes!");
     println!("Max seat id: {}", max_seat_id);
 }
 
 fn part2() {
-    let my_seat_id = io::stdin()
-        .lock()
-        .lines()
-        .map(|l| decode_seat(&l.unwrap()))
-        .map(seat_id)
-        .sorted()
-        .tuple_windows::<(_, _, _)>()
-        .find_map(|(p, c, n)| if c == p+2 && n == c+1 { Some(p+1) } else { None } )
-        .expect("Could not find any row with a single empty seat!");
-    println!("My seat id is: {}", my_seat_id);
+    let passes = read_passes();
+    let missing = missing_seats(&passes);
+    // With `--map`, draw the whole plane
+    if std::env::args().any(|arg| arg == "--map") {
+        println!("{}", render_seat_map(&passes, &missing));
+    }
+    match missing[..] {
+        [seat] => println!("My seat id is: {} ({})", seat.id(), seat),
+        [] => println!("Could not find any row with a single empty seat!"),
+        _ => println!(
+            "More than one seat could be mine: {}",
+            missing.iter().map(BoardingPass::id).join(", ")
+        ),
+    }
 }
 
 pub fn main() {
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(),
         Some(p) if p == "part2" => part2(),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let pass: BoardingPass = "BFFFBBFRRR".parse().unwrap();
+        assert_eq!(pass, BoardingPass { row: 70, column: 7 });
+        assert_eq!(pass.id(), 567);
+        assert_eq!(pass.to_string(), "BFFFBBFRRR");
+        assert_eq!(BoardingPass::from_id(567), Some(pass));
+        assert_eq!(BoardingPass::<7, 3>::from_id(1024), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "BFF".parse::<BoardingPass>(),
+            Err(PassError::Length {
+                expected: 10,
+                found: 3
+            })
+        );
+        assert_eq!(
+            "BFFFBBFRRX".parse::<BoardingPass>(),
+            Err(PassError::InvalidLetter {
+                position: 9,
+                letter: 'X'
+            })
+        );
+        // Row and column letters can't be swapped
+        assert_eq!(
+            "RFFFBBFRRR".parse::<BoardingPass>(),
+            Err(PassError::InvalidLetter {
+                position: 0,
+                letter: 'R'
+            })
+        );
+    }
+
+    #[test]
+    fn test_custom_size() {
+        let pass: BoardingPass<2, 1> = "BFR".parse().unwrap();
+        assert_eq!(pass, BoardingPass { row: 2, column: 1 });
+        assert_eq!(pass.id(), 5);
+        assert_eq!(pass.to_string(), "BFR");
+        assert_eq!(BoardingPass::<2, 1>::from_id(8), None);
+        assert_eq!(
+            "BFFFBBFRRR".parse::<BoardingPass<2, 1>>(),
+            Err(PassError::Length {
+                expected: 3,
+                found: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_seats() {
+        let passes: Vec<BoardingPass<2, 1>> = [1, 2, 4, 5, 7]
+            .iter()
+            .map(|&id| BoardingPass::from_id(id).unwrap())
+            .collect();
+        let missing = missing_seats(&passes);
+        assert_eq!(
+            missing.iter().map(BoardingPass::id).collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        // Gaps of more than one seat don't count
+        assert!(missing_seats(&[passes[0], passes[4]]).is_empty());
+        assert!(missing_seats::<2, 1>(&[]).is_empty());
+    }
+}
@@ -1,10 +1,13 @@
 use itertools::Itertools;
-use petgraph::algo::has_path_connecting;
+use petgraph::algo::tarjan_scc;
+use petgraph::dot::Dot;
 use petgraph::graphmap::GraphMap;
-use petgraph::Directed;
+use petgraph::{Directed, Direction};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Read};
 
-fn parse_rule(rule: &str) -> Vec<(&str, &str, usize)> {
+// The outer bag, and the inner bags with how many of each
+fn parse_rule(rule: &str) -> (&str, Vec<(&str, usize)>) {
     let (source, targets) = rule
         .splitn(2, " contain ")
         .collect_tuple()
@@ -14,53 +17,193 @@ fn parse_rule(rule: &str) -> Vec<(&str, &str, usize)> {
         .collect_tuple()
         .expect("Invalid rule");
     if targets == "no other bags." {
-        return vec![];
+        return (source, vec![]);
     }
-    targets
+    let targets = targets
         .split(", ")
         .map(|t| {
             let (n, node) = t.splitn(2, ' ').collect_tuple().expect("Invalid rule");
             let (_, node) = node.rsplitn(2, ' ').collect_tuple().expect("Invalid rule");
-            (source, node, n.parse().unwrap())
+            (node, n.parse().unwrap())
         })
-        .collect_vec()
+        .collect_vec();
+    (source, targets)
 }
 
-fn graph_from_rules(rules: &str) -> GraphMap<&str, usize, Directed> {
-    let edges: Vec<(&str, &str, usize)> = rules.lines().flat_map(parse_rule).collect();
-    GraphMap::from_edges(&edges)
+// Edges go from the outer bag to the inner one, weighted by how many inner
+// bags the outer one contains
+struct BagRules<'a> {
+    graph: GraphMap<&'a str, usize, Directed>,
+    // Every bag after all the bags it contains
+    inner_first: Vec<&'a str>,
 }
 
-fn count_contained_bags(graph: &GraphMap<&str, usize, Directed>, node_ref: &str) -> usize {
-    graph
-        .edges(node_ref)
-        .map(|(_outer, inner, count)| count_contained_bags(graph, inner) * count + count)
-        .sum::<usize>()
+impl<'a> BagRules<'a> {
+    fn new(rules: &'a str) -> Result<Self, String> {
+        let mut graph = GraphMap::new();
+        for (outer, targets) in rules.lines().map(parse_rule) {
+            graph.add_node(outer);
+            for (inner, count) in targets {
+                graph.add_edge(outer, inner, count);
+            }
+        }
+        // Tarjan's components come out inner bags first; with no cycles each
+        // is a single bag that doesn't contain itself
+        let components = tarjan_scc(&graph);
+        if let Some(cycle) = components
+            .iter()
+            .find(|c| c.len() > 1 || graph.contains_edge(c[0], c[0]))
+        {
+            return Err(format!(
+                "the rules are cyclic: {} contain each other",
+                cycle.iter().join(", ")
+            ));
+        }
+        let inner_first = components.into_iter().flatten().collect();
+        Ok(Self { graph, inner_first })
+    }
+
+    fn bag(&self, colour: &str) -> Result<&'a str, String> {
+        self.graph
+            .nodes()
+            .find(|&bag| bag == colour)
+            .ok_or_else(|| format!("no rule mentions {} bags", colour))
+    }
+
+    // All the bags that eventually contain the given one
+    fn containers(&self, colour: &str) -> Result<HashSet<&'a str>, String> {
+        let mut stack: Vec<&'a str> = self
+            .graph
+            .neighbors_directed(self.bag(colour)?, Direction::Incoming)
+            .collect();
+        let mut found: HashSet<&'a str> = stack.iter().copied().collect();
+        while let Some(bag) = stack.pop() {
+            for outer in self.graph.neighbors_directed(bag, Direction::Incoming) {
+                if found.insert(outer) {
+                    stack.push(outer);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    // How many bags every bag contains, each computed once from the counts
+    // of the bags directly inside it
+    fn contained_counts(&self) -> HashMap<&'a str, usize> {
+        let mut counts = HashMap::new();
+        for &bag in &self.inner_first {
+            let count = self
+                .graph
+                .edges(bag)
+                .map(|(_, inner, n)| n * (1 + counts[inner]))
+                .sum();
+            counts.insert(bag, count);
+        }
+        counts
+    }
+
+    fn to_dot(&self) -> String {
+        format!("{}", Dot::new(&self.graph))
+    }
+}
+
+// The bag to ask about is given with `--bag`, shiny gold by default
+fn queried_colour() -> String {
+    std::env::args()
+        .skip_while(|arg| arg != "--bag")
+        .nth(1)
+        .unwrap_or_else(|| "shiny gold".to_owned())
 }
 
-fn part1(graph: &GraphMap<&str, usize, Directed>) {
-    let count = graph
-        .nodes()
-        .filter(|&n| has_path_connecting(graph, n, "shiny gold".as_ref(), None))
-        .count();
+fn part1(rules: &BagRules) -> Result<(), String> {
+    let colour = queried_colour();
+    let count = rules.containers(&colour)?.len();
     println!(
-        "{:?} bag types can (transitively) contain shiny gold bags",
-        count - 1
+        "{:?} bag types can (transitively) contain {} bags",
+        count, colour
     );
+    Ok(())
 }
 
-fn part2(graph: &GraphMap<&str, usize, Directed>) {
-    let res = count_contained_bags(graph, "shiny gold");
-    println!("{} bags are required inside one shiny gold bag", res);
+fn part2(rules: &BagRules) -> Result<(), String> {
+    let colour = queried_colour();
+    let res = rules.contained_counts()[rules.bag(&colour)?];
+    println!("{} bags are required inside one {} bag", res, colour);
+    Ok(())
 }
 
 pub fn main() {
     let mut buf = String::new();
     io::stdin().read_to_string(&mut buf).expect("Error reading input");
-    let graph = graph_from_rules(buf.as_str());
-    match std::env::args().skip(1).next() {
-        Some(p) if p == "part1" => part1(&graph),
-        Some(p) if p == "part2" => part2(&graph),
-        _ => println!("Please specify a part (part1 | part2)"),
+    let rules = match BagRules::new(buf.as_str()) {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("Invalid bag rules: {}", e);
+            return;
+        }
+    };
+    // With `--dot`, print the rules as a Graphviz graph instead
+    if std::env::args().any(|arg| arg == "--dot") {
+        print!("{}", rules.to_dot());
+        return;
+    }
+    let res = match std::env::args().nth(1) {
+        Some(p) if p == "part1" => part1(&rules),
+        Some(p) if p == "part2" => part2(&rules),
+        _ => {
+            println!("Please specify a part (part1 | part2)");
+            Ok(())
+        }
+    };
+    if let Err(e) = res {
+        eprintln!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../../inputs/day7-example.in");
+    const EXAMPLE2: &str = include_str!("../../inputs/day7-example2.in");
+
+    #[test]
+    fn test_containers() {
+        let rules = BagRules::new(EXAMPLE).unwrap();
+        let containers = rules.containers("shiny gold").unwrap();
+        let mut containers: Vec<&str> = containers.into_iter().collect();
+        containers.sort_unstable();
+        assert_eq!(
+            containers,
+            vec!["bright white", "dark orange", "light red", "muted yellow"]
+        );
+        assert!(rules.containers("light red").unwrap().is_empty());
+        assert!(rules.containers("plaid purple").is_err());
+    }
+
+    #[test]
+    fn test_contained_counts() {
+        let rules = BagRules::new(EXAMPLE).unwrap();
+        let counts = rules.contained_counts();
+        assert_eq!(counts["shiny gold"], 32);
+        assert_eq!(counts["faded blue"], 0);
+        assert_eq!(counts["vibrant plum"], 11);
+        let rules = BagRules::new(EXAMPLE2).unwrap();
+        assert_eq!(rules.contained_counts()["shiny gold"], 126);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let error = BagRules::new("light red bags contain 1 light red bag.").err();
+        assert_eq!(
+            error,
+            Some("the rules are cyclic: light red contain each other".to_owned())
+        );
+        let rules = "\
+light red bags contain 2 dark blue bags.
+dark blue bags contain 1 pale green bag, 3 faded black bags.
+pale green bags contain 1 light red bag.
+faded black bags contain no other bags.";
+        assert!(BagRules::new(rules).is_err());
     }
 }
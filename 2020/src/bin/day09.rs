@@ -1,51 +1,40 @@
 use std::io::{self, BufRead};
 
-use aoc2020::utils;
+use aoc2020::window;
 
-const WINDOW_SIZE: usize = 25;
+const DEFAULT_WINDOW_SIZE: usize = 25;
 
-fn find_target(numbers: &Vec<u64>) -> Option<u64> {
-    numbers
-        .windows(WINDOW_SIZE + 1)
-        .find_map(|window| {
-            let (previous25, n) = (&window[..WINDOW_SIZE], window[WINDOW_SIZE]);
-            match utils::find_couple_with_sum(previous25, n) {
-                None => Some(n),
-                Some(_) => None
-            }
-        })
+// The preamble length can be passed after the part, e.g. `part1 5` for the example
+fn window_size() -> usize {
+    std::env::args()
+        .nth(2)
+        .map(|arg| arg.parse().expect("Invalid window size"))
+        .unwrap_or(DEFAULT_WINDOW_SIZE)
 }
 
-fn part1(numbers: &Vec<u64>) {
+fn find_target(numbers: &[i64]) -> Option<i64> {
+    window::first_not_pair_sum(numbers, window_size()).map(|(_, n)| n)
+}
+
+fn part1(numbers: &[i64]) {
     match find_target(numbers) {
         Some(n) => println!("{} is the target", n),
         None => panic!("All number are a sum of two of the previous ones!")
     }
 }
 
-fn part2(numbers: &Vec<u64>) {
+fn part2(numbers: &[i64]) {
     let target = find_target(numbers).unwrap();
-    let mut from = 1;
-    let mut to = 2;
-    let mut sum = numbers[from] + numbers[to];
-    while sum != target {
-        if sum < target || from + 1 == to {
-            to += 1;
-            sum += numbers[to];
-        } else {
-            sum -= numbers[from];
-            from += 1;
-        }
-    }
-    let slice = &numbers[from..=to];
+    let range = window::contiguous_sum(numbers, target, 2).expect("No contiguous set sums up to the target");
+    let slice = &numbers[range];
     let min = slice.iter().min().unwrap();
     let max = slice.iter().max().unwrap();
     println!("{} is the encryption weakness ({}, {})", min + max, min, max);
 }
 
 pub fn main() {
-    let numbers: Vec<u64> = io::stdin().lock().lines().map(|l| l.unwrap().parse::<u64>().unwrap()).collect();
-    match std::env::args().skip(1).next() {
+    let numbers: Vec<i64> = io::stdin().lock().lines().map(|l| l.unwrap().parse::<i64>().unwrap()).collect();
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(&numbers),
         Some(p) if p == "part2" => part2(&numbers),
         _ => println!("Please specify a part (part1 | part2)"),
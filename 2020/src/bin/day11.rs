@@ -1,150 +1,150 @@
-use itertools::Itertools;
-use std::cmp::min;
+use aoc2020::automaton::{Automaton, Fate, Rule, Topology};
+use std::convert::TryFrom;
 use std::io::{self, BufRead};
 
-type Grid = Vec<Vec<char>>;
-
-fn simulate_one<F>(grid: &Grid, seats_to_check_generator: &F, min_to_leave: usize) -> Grid
-// where F: Fn(&Grid, (usize, usize)) -> impl Iterator<Item=(usize, usize)> {
-where
-    F: Fn(&Grid, (usize, usize)) -> Vec<(usize, usize)>,
-{
-    let mut next_grid = grid.clone();
-    for (r, row) in grid.iter().enumerate() {
-        for (c, cell) in row.iter().enumerate() {
-            let occupied = seats_to_check_generator(grid, (r, c))
-                .iter()
-                .filter(|(ar, ac)| grid[*ar][*ac] == '#')
-                .count();
-            next_grid[r][c] = match (cell, occupied) {
-                ('L', 0) => '#',
-                ('#', n) if n >= min_to_leave => 'L',
-                (&s, _) => s,
-            }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Cell {
+    Floor,
+    Empty,
+    Occupied,
+}
+
+impl TryFrom<char> for Cell {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '.' => Ok(Cell::Floor),
+            'L' => Ok(Cell::Empty),
+            '#' => Ok(Cell::Occupied),
+            _ => Err(format!("invalid cell {}", c)),
         }
     }
-    next_grid
 }
 
-fn simulate_until_stable<F>(grid: &Grid, generator: F, min_to_leave: usize) -> (usize, Grid)
-where
-    F: Fn(&Grid, (usize, usize)) -> Vec<(usize, usize)>,
-{
-    let mut grid: Grid = grid.clone();
-    std::iter::from_fn(|| match simulate_one(&grid, &generator, min_to_leave) {
-        next if next == grid => None,
-        next => {
-            grid = next;
-            Some(grid.to_owned())
+impl From<Cell> for char {
+    fn from(cell: Cell) -> Self {
+        match cell {
+            Cell::Floor => '.',
+            Cell::Empty => 'L',
+            Cell::Occupied => '#',
         }
-    })
-    .into_iter()
-    .enumerate()
-    .last()
-    .unwrap()
+    }
 }
 
-// fn adjacent(grid: &Grid, position: (usize, usize)) -> impl Iterator<Item=(usize, usize)> {
-fn adjacent(grid: &Grid, position: (usize, usize)) -> Vec<(usize, usize)> {
-    let (width, height) = (grid[0].len(), grid.len());
-    let (r, c) = position;
-    (r.saturating_sub(1)..=min(r + 1, height - 1))
-        .cartesian_product(c.saturating_sub(1)..=min(c + 1, width - 1))
-        .filter(|(ar, ac)| *ar != r || *ac != c)
-        .collect()
+// Empty seats get taken when nobody is around, occupied ones are left when
+// at least `tolerance` neighbours are occupied
+struct Seating {
+    tolerance: usize,
 }
 
-fn within_bounds((width, height): (isize, isize), (r, c): (isize, isize)) -> bool {
-    r >= 0 && r < height && c >= 0 && c < width
+impl Rule for Seating {
+    type State = Cell;
+
+    fn is_live(&self, state: Cell) -> bool {
+        state == Cell::Occupied
+    }
+
+    fn next(&self, state: Cell, occupied: usize) -> Cell {
+        match (state, occupied) {
+            (Cell::Empty, 0) => Cell::Occupied,
+            (Cell::Occupied, n) if n >= self.tolerance => Cell::Empty,
+            (s, _) => s,
+        }
+    }
 }
 
-fn visible(grid: &Grid, position: (usize, usize)) -> Vec<(usize, usize)> {
-    let (r, c) = (position.0 as isize, position.1 as isize);
-    let (width, height) = (grid[0].len(), grid.len());
-    [
-        (-1, 0),
-        (-1, 1),
-        (0, 1),
-        (1, 1),
-        (1, 0),
-        (1, -1),
-        (0, -1),
-        (-1, -1),
-    ]
-    .iter()
-    .map(|&(v, h): &(isize, isize)| {
-        (1..)
-            .map(move |n: isize| (r + v * n, c + h * n))
-            .take_while(|&pos| within_bounds((width as isize, height as isize), pos))
-            .map(|(r, c)| (r as usize, c as usize))
-            .find(|&(r, c)| grid[r][c] != '.')
-    })
-    .flatten()
-    .collect::<Vec<_>>()
+struct Floorplan {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
 }
 
-// Idea: because the visible seats from each position depend only on the
-// floorplan and not on the current generation empty/occupied seats, we can
-// preocompute the visible seats into an hashmap and then just return from there
-// (cool, but more simply we could cache the invocations to `visible` for the
-// same effect)
-// fn build_visible_generator(_grid: &Grid) -> impl Fn(&Grid, (usize, usize)) -> Vec<(usize, usize)> {
-
-//     // call visible() for every position, and save the result in an
-//     // hashmap
-//     let visible_seats: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
-
-//     // return a precomputed version of `visible` that just reads from
-//     // the precomputed hashmap
-//     let k = move |_grid: &Grid, position: (usize, usize)| {
-//         visible_seats
-//             .get(&position)
-//             .expect("something wrong")
-//             .to_owned()
-//     };
-//     k
-// }
-
-fn count_occupied(grid: &Grid) -> usize {
-    grid.iter()
-        .map(|x| x.iter())
-        .flatten()
-        .filter(|x| x == &&'#')
-        .count()
+impl Floorplan {
+    fn is_seat(&self, r: usize, c: usize) -> bool {
+        self.cells[r * self.width + c] != Cell::Floor
+    }
 }
 
-pub fn print_grid(grid: &Grid) {
-    let k = grid.iter().map(|x| x.iter().join("")).join("\n");
-    println!("{}", k);
+fn read_floorplan() -> Floorplan {
+    let rows: Vec<Vec<Cell>> = io::stdin()
+        .lock()
+        .lines()
+        .map(|l| {
+            l.expect("Could not read line")
+                .chars()
+                .map(Cell::try_from)
+                .collect::<Result<_, _>>()
+                .expect("Invalid floorplan")
+        })
+        .collect();
+    let width = rows.first().map_or(0, |row| row.len());
+    assert!(
+        rows.iter().all(|row| row.len() == width),
+        "Rows of different lengths"
+    );
+    Floorplan {
+        width,
+        height: rows.len(),
+        cells: rows.into_iter().flatten().collect(),
+    }
+}
+
+fn print_grid(automaton: &Automaton<Seating>) {
+    for row in automaton.rows() {
+        println!(
+            "{}",
+            row.iter().map(|&cell| char::from(cell)).collect::<String>()
+        );
+    }
+}
+
+fn simulate(floorplan: &Floorplan, topology: Topology, tolerance: usize) {
+    let mut automaton = Automaton::new(Seating { tolerance }, topology, floorplan.cells.clone());
+    // With `--trace`, show how many seats are taken generation by generation
+    let trace = std::env::args().any(|arg| arg == "--trace");
+    let fate = automaton.settle_with(|automaton| {
+        if trace {
+            println!(
+                "generation {}: {} occupied seats",
+                automaton.generation(),
+                automaton.count(Cell::Occupied)
+            );
+        }
+    });
+    match fate {
+        Fate::Stable { generation } => println!(
+            "{} occupied seats once stable after {} generations",
+            automaton.count(Cell::Occupied),
+            generation
+        ),
+        Fate::Cycle { start, period } => println!(
+            "Seats never settle: from generation {} they cycle every {} generations",
+            start, period
+        ),
+    }
+    if std::env::args().any(|arg| arg == "--show") {
+        print_grid(&automaton);
+    }
 }
 
-fn part1(grid: &Grid) {
-    let (generations, final_grid) = simulate_until_stable(grid, adjacent, 4);
-    println!(
-        "{} occupied seats once stable after {} generations",
-        count_occupied(&final_grid),
-        generations
-    )
+fn part1(floorplan: &Floorplan) {
+    let topology = Topology::adjacent(floorplan.width, floorplan.height);
+    simulate(floorplan, topology, 4);
 }
 
-fn part2(grid: &Grid) {
-    let (generations, final_grid) = simulate_until_stable(grid, visible, 5);
-    println!(
-        "{} occupied seats once stable after {} generations",
-        count_occupied(&final_grid),
-        generations
-    )
+fn part2(floorplan: &Floorplan) {
+    let topology = Topology::line_of_sight(floorplan.width, floorplan.height, |r, c| {
+        floorplan.is_seat(r, c)
+    });
+    simulate(floorplan, topology, 5);
 }
 
 pub fn main() {
-    let grid: Grid = io::stdin()
-        .lock()
-        .lines()
-        .map(|l| l.unwrap().chars().collect_vec())
-        .collect();
-    match std::env::args().skip(1).next() {
-        Some(p) if p == "part1" => part1(&grid),
-        Some(p) if p == "part2" => part2(&grid),
+    let floorplan = read_floorplan();
+    match std::env::args().nth(1) {
+        Some(p) if p == "part1" => part1(&floorplan),
+        Some(p) if p == "part2" => part2(&floorplan),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
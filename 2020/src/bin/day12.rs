@@ -1,138 +1,138 @@
-use core::panic;
+use aoc_common::vec2::Vec2;
 use std::io::{self, BufRead};
+use std::str::FromStr;
 
 #[derive(Clone, Copy, Debug)]
-enum Direction {
-    North,
-    East,
-    South,
-    West,
+enum Command {
+    // N, S, E and W
+    Move(Vec2, isize),
+    // L and R, in quarter turns, clockwise when positive
+    Turn(isize),
+    Forward(isize),
 }
 
-#[derive(Clone, Debug)]
-struct State {
-    direction: Direction,
-    ship: (isize, isize),
-    waypoint: (isize, isize),
-}
+impl FromStr for Command {
+    type Err = String;
 
-#[derive(Clone, Copy, Debug)]
-enum Command {
-    N(isize),
-    S(isize),
-    E(isize),
-    W(isize),
-    L(isize),
-    R(isize),
-    F(isize),
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let action = chars.next().ok_or("empty command")?;
+        let value: isize = chars
+            .as_str()
+            .parse()
+            .map_err(|_| format!("invalid value in {}", s))?;
+        let quarter_turns = |degrees: isize| match degrees % 90 {
+            0 => Ok(degrees / 90),
+            _ => Err(format!("can only turn by multiples of 90 degrees: {}", s)),
+        };
+        match action {
+            'N' => Ok(Command::Move(Vec2::NORTH, value)),
+            'S' => Ok(Command::Move(Vec2::SOUTH, value)),
+            'E' => Ok(Command::Move(Vec2::EAST, value)),
+            'W' => Ok(Command::Move(Vec2::WEST, value)),
+            'L' => quarter_turns(-value).map(Command::Turn),
+            'R' => quarter_turns(value).map(Command::Turn),
+            'F' => Ok(Command::Forward(value)),
+            _ => Err(format!("invalid action {}", action)),
+        }
+    }
 }
 
-fn rotate_ship(ship_direction: Direction, degrees: isize) -> Direction {
-    (0..(degrees.abs() / 90)).fold(ship_direction, |dir, _| {
-        match (dir, degrees.is_negative()) {
-            (Direction::North, false) => Direction::East,
-            (Direction::East, false) => Direction::South,
-            (Direction::South, false) => Direction::West,
-            (Direction::West, false) => Direction::North,
-            (Direction::North, true) => Direction::West,
-            (Direction::East, true) => Direction::North,
-            (Direction::South, true) => Direction::East,
-            (Direction::West, true) => Direction::South,
-        }
-    })
+trait Navigator {
+    fn position(&self) -> Vec2;
+    fn execute(&mut self, command: Command);
 }
 
-fn rotate_waypoint(waypoint: (isize, isize), degrees: isize) -> (isize, isize) {
-    (0..(degrees.abs() / 90)).fold(waypoint, |(rel_x, rel_y), _| {
-        if degrees.is_positive() {
-            (-rel_y, rel_x)
-        } else {
-            (rel_y, -rel_x)
-        }
-    })
+// Moves and turns apply to the ship itself
+struct Ship {
+    position: Vec2,
+    heading: Vec2,
 }
 
-fn move_part1(mut state: State, command: &Command) -> State {
-    match *command {
-        Command::N(v) => state.ship.1 -= v,
-        Command::S(v) => state.ship.1 += v,
-        Command::E(v) => state.ship.0 += v,
-        Command::W(v) => state.ship.0 -= v,
-        Command::L(v) => state.direction = rotate_ship(state.direction, -v),
-        Command::R(v) => state.direction = rotate_ship(state.direction, v),
-        Command::F(v) => match state.direction {
-            Direction::North => return move_part1(state, &Command::N(v)),
-            Direction::East => return move_part1(state, &Command::E(v)),
-            Direction::South => return move_part1(state, &Command::S(v)),
-            Direction::West => return move_part1(state, &Command::W(v)),
-        },
-    };
-    state
+// Moves and turns apply to the waypoint, relative to the ship, which is
+// where the ship goes forward towards
+struct WaypointShip {
+    position: Vec2,
+    waypoint: Vec2,
 }
 
-fn move_part2(mut state: State, command: &Command) -> State {
-    match *command {
-        Command::N(v) => state.waypoint.1 -= v,
-        Command::S(v) => state.waypoint.1 += v,
-        Command::E(v) => state.waypoint.0 += v,
-        Command::W(v) => state.waypoint.0 -= v,
-        Command::L(v) => state.waypoint = rotate_waypoint(state.waypoint, -v),
-        Command::R(v) => state.waypoint = rotate_waypoint(state.waypoint, v),
-        Command::F(v) => {
-            state.ship = (
-                state.ship.0 + state.waypoint.0 * v,
-                state.ship.1 + state.waypoint.1 * v,
-            )
+impl Navigator for Ship {
+    fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Move(direction, v) => self.position += direction * v,
+            Command::Turn(quarter_turns) => self.heading = self.heading.rotate(quarter_turns),
+            Command::Forward(v) => self.position += self.heading * v,
         }
     }
-    state
 }
 
-fn parse_command(cmd: &str) -> Command {
-    let (c, value) = cmd.split_at(1);
-    match (c.chars().next(), value.parse::<isize>()) {
-        (Some('N'), Ok(v)) => Command::N(v),
-        (Some('E'), Ok(v)) => Command::E(v),
-        (Some('S'), Ok(v)) => Command::S(v),
-        (Some('W'), Ok(v)) => Command::W(v),
-        (Some('L'), Ok(v)) => Command::L(v),
-        (Some('R'), Ok(v)) => Command::R(v),
-        (Some('F'), Ok(v)) => Command::F(v),
-        _ => panic!(&format!("Invalid command string {}", cmd)),
+impl Navigator for WaypointShip {
+    fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Move(direction, v) => self.waypoint += direction * v,
+            Command::Turn(quarter_turns) => self.waypoint = self.waypoint.rotate(quarter_turns),
+            Command::Forward(v) => self.position += self.waypoint * v,
+        }
     }
 }
 
-fn l1_distance(coordinates: (isize, isize)) -> isize {
-    coordinates.0.abs() + coordinates.1.abs()
+// Where the ship is at the start and after every command
+fn navigate<N: Navigator>(navigator: &mut N, commands: &[Command]) -> Vec<Vec2> {
+    std::iter::once(navigator.position())
+        .chain(commands.iter().map(|&command| {
+            navigator.execute(command);
+            navigator.position()
+        }))
+        .collect()
+}
+
+fn generic_solution<N: Navigator>(mut navigator: N, commands: &[Command]) {
+    let trajectory = navigate(&mut navigator, commands);
+    // With `--trajectory`, dump the positions as x,y lines to plot the path
+    if std::env::args().any(|arg| arg == "--trajectory") {
+        for position in &trajectory {
+            println!("{},{}", position.x, position.y);
+        }
+    }
+    println!("{}", navigator.position().manhattan());
 }
 
-fn part1(commands: &Vec<Command>) {
-    let state = State {
-        ship: (0, 0),
-        waypoint: (10, -1),
-        direction: Direction::East,
+fn part1(commands: &[Command]) {
+    let ship = Ship {
+        position: Vec2::ZERO,
+        heading: Vec2::EAST,
     };
-    let final_state = commands.iter().fold(state, move_part1);
-    println!("{}", l1_distance(final_state.ship));
+    generic_solution(ship, commands);
 }
 
-fn part2(commands: &Vec<Command>) {
-    let state = State {
-        ship: (0, 0),
-        waypoint: (10, -1),
-        direction: Direction::East,
+fn part2(commands: &[Command]) {
+    let ship = WaypointShip {
+        position: Vec2::ZERO,
+        waypoint: Vec2::new(10, -1),
     };
-    let final_state = commands.iter().fold(state, move_part2);
-    println!("{}", l1_distance(final_state.ship));
+    generic_solution(ship, commands);
 }
 
 pub fn main() {
     let commands: Vec<Command> = io::stdin()
         .lock()
         .lines()
-        .map(|l| parse_command(l.unwrap().as_str()))
+        .enumerate()
+        .map(|(i, l)| {
+            l.expect("Could not read line")
+                .parse()
+                .unwrap_or_else(|e| panic!("line {}: {}", i + 1, e))
+        })
         .collect();
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(&commands),
         Some(p) if p == "part2" => part2(&commands),
         _ => println!("Please specify a part (part1 | part2)"),
@@ -1,21 +1,13 @@
 use itertools::Itertools;
 use std::io::{self, Read};
 
-fn bus_departure_time(prev_departure_time: u64, prev_cycle_time: u64, bus_cycle_time: u64) -> u64 {
-    let mut timestamp = prev_departure_time + 1;
-    std::iter::from_fn(|| {
-        timestamp += prev_cycle_time;
-        Some(timestamp)
-    })
-    .find(|t| t % bus_cycle_time == 0)
-    .expect("something strange happened")
-}
+use aoc2020::number_theory;
 
 fn first_departure_after(bus_cycle: u64, earliest_departure: u64) -> u64 {
     (earliest_departure as f64 / bus_cycle as f64).ceil() as u64 * bus_cycle
 }
 
-fn part1(earliest_departure: u64, buses: &Vec<u64>) {
+fn part1(earliest_departure: u64, buses: &[u64]) {
     let (bus_id, departure_time) = buses
         .iter()
         .filter(|b| **b != 0)
@@ -29,26 +21,25 @@ fn part1(earliest_departure: u64, buses: &Vec<u64>) {
     );
 }
 
-fn part2(buses: &Vec<u64>) {
-    let (last_bus_departure_time, cycle_time) = buses
+// Bus at position i must depart at t + i, that is t = -i (mod bus)
+fn earliest_aligned_departure(buses: &[u64]) -> Option<(u64, u64)> {
+    let congruences: Vec<(i64, i64)> = buses
         .iter()
-        .skip(1)
-        .fold((0u64, buses[0]), |(acc_departure_time, acc_cycle_time), &bus| {
-            match bus {
-                0 => (acc_departure_time + 1, acc_cycle_time),
-                _ => (
-                    bus_departure_time(acc_departure_time, acc_cycle_time, bus),
-                    // all bus cycle times are prime => their product is coprime with new bus
-                    // => the new total cycle time is simply prev_cycle_time * bus_cycle_time
-                    acc_cycle_time * bus,
-                ),
-            }
-        },
-    );
-    print!(
-        "{:?} is the first bus departure time that satisfy constraints (cycles every {})",
-        last_bus_departure_time - buses.len() as u64 + 1, cycle_time
-    );
+        .enumerate()
+        .filter(|(_, &bus)| bus != 0)
+        .map(|(i, &bus)| (-(i as i64), bus as i64))
+        .collect();
+    number_theory::crt(&congruences).map(|(t, cycle)| (t as u64, cycle as u64))
+}
+
+fn part2(buses: &[u64]) {
+    match earliest_aligned_departure(buses) {
+        Some((timestamp, cycle_time)) => println!(
+            "{} is the first bus departure time that satisfy constraints (cycles every {})",
+            timestamp, cycle_time
+        ),
+        None => println!("No timestamp satisfies the constraints"),
+    }
 }
 
 pub fn main() {
@@ -63,7 +54,7 @@ pub fn main() {
         .split(',')
         .map(|sn| sn.parse::<u64>().unwrap_or(0))
         .collect();
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(earliest_departure, &buses),
         Some(p) if p == "part2" => part2(&buses),
         _ => println!("Please specify a part (part1 | part2)"),
@@ -1,130 +1,300 @@
 use std::io::{self, BufRead};
-use std::collections::HashMap;
+use std::str::FromStr;
 
-mod decoder_v1 {
-    use itertools::Itertools;
+const ADDRESS_BITS: usize = 36;
 
-    #[derive(Debug)]
-    pub enum Command {
-        Mem(u64, u64),
-        Mask(u64, u64),
-    }
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Mask {
+    ones: u64,
+    zeros: u64,
+    floating: u64,
+}
 
-    fn parse_mask(line: &str) -> Command {
-        let (_, mask) = line
-            .split(" = ")
-            .collect_tuple()
-            .expect("invalid mask command");
-        let mask_and = u64::from_str_radix(&mask.replace("X", "1"), 2).unwrap();
-        let mask_or = u64::from_str_radix(&mask.replace("X", "0"), 2).unwrap();
-        Command::Mask(mask_and, mask_or)
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Mask(Mask),
+    Mem { address: u64, value: u64 },
+}
 
-    fn parse_mem(line: &str) -> Command {
-        let (a, b) = line.splitn(2, " = ").collect_tuple().expect("invalid line");
-        let value: u64 = b.parse().expect("invalid value");
-        let address: u64 = a[4..a.len()-1].parse().expect("invalid address");
-        Command::Mem(address, value)
+impl FromStr for Mask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != ADDRESS_BITS {
+            return Err(format!("mask must be {} bits long", ADDRESS_BITS));
+        }
+        s.chars().try_fold(Mask::default(), |mask, c| {
+            let shifted = Mask {
+                ones: mask.ones << 1,
+                zeros: mask.zeros << 1,
+                floating: mask.floating << 1,
+            };
+            match c {
+                '1' => Ok(Mask {
+                    ones: shifted.ones | 1,
+                    ..shifted
+                }),
+                '0' => Ok(Mask {
+                    zeros: shifted.zeros | 1,
+                    ..shifted
+                }),
+                'X' => Ok(Mask {
+                    floating: shifted.floating | 1,
+                    ..shifted
+                }),
+                _ => Err(format!("invalid mask bit {}", c)),
+            }
+        })
     }
+}
 
-    pub fn parse_command(line: &str) -> Command {
-        match &line[0..3] {
-            "mas" => parse_mask(line),
-            "mem" => parse_mem(line),
-            _ => panic!("invalid command")
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut sides = line.splitn(2, " = ");
+        let (target, value) = match (sides.next(), sides.next()) {
+            (Some(target), Some(value)) => (target, value),
+            _ => return Err(format!("expected an assignment: {}", line)),
+        };
+        if target == "mask" {
+            return value.parse().map(Command::Mask);
         }
+        let address = target
+            .strip_prefix("mem[")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| format!("invalid assignment target {}", target))?
+            .parse()
+            .map_err(|_| format!("invalid address in {}", target))?;
+        let value = value
+            .parse()
+            .map_err(|_| format!("invalid value {}", value))?;
+        Ok(Command::Mem { address, value })
     }
 }
 
-mod decoder_v2 {
-    use std::iter::FromIterator;
-    use itertools::Itertools;
-
-    #[derive(Debug)]
-    pub enum Command {
-        Mem(u64, u64),
-        Mask(Vec<(u64, u64)>),
-    }
-
-    fn parse_mask(line: &str) -> Command {
-        let (_, mask) = line
-            .split(" = ")
-            .collect_tuple()
-            .expect("invalid mask command");
-        let v = mask.chars()
-            .map(|c| match c {
-                'X' => vec!['0', '1'],
-                '1' => vec!['1'],
-                '0' => vec!['X'],
-                _ => panic!("unexpected char!")
-            })
-            .multi_cartesian_product()
-            .map(|v| String::from_iter(v))
-            .map(|m| {
-                let mask_and = u64::from_str_radix(&m.replace("X", "1"), 2).unwrap();
-                let mask_or = u64::from_str_radix(&m.replace("X", "0"), 2).unwrap();
-                (mask_and, mask_or)
-            })
-            .collect_vec();
-        Command::Mask(v)
-    }
-
-    fn parse_mem(line: &str) -> Command {
-        match super::decoder_v1::parse_command(line) {
-            super::decoder_v1::Command::Mem(addr, val) => Command::Mem(addr, val),
-            _ => panic!("what??")
+// A set of addresses: bits in `floating` take both values, the others are
+// the ones in `fixed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct AddressPattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl AddressPattern {
+    fn exact(address: u64) -> Self {
+        Self {
+            fixed: address,
+            floating: 0,
         }
     }
 
-    pub fn parse_command(line: &str) -> Command {
-        match &line[0..3] {
-            "mas" => parse_mask(line),
-            "mem" => parse_mem(line),
-            _ => panic!("invalid command")
+    fn len(&self) -> u64 {
+        1 << self.floating.count_ones()
+    }
+
+    fn intersects(&self, other: &AddressPattern) -> bool {
+        let both_fixed = !self.floating & !other.floating;
+        (self.fixed ^ other.fixed) & both_fixed == 0
+    }
+
+    // Disjoint patterns covering the addresses of self not in other. Every
+    // bit floating here but fixed there splits off the half that disagrees
+    // with other, and what's left at the end is entirely inside other.
+    fn subtract(&self, other: &AddressPattern) -> Vec<AddressPattern> {
+        if !self.intersects(other) {
+            return vec![*self];
         }
+        let mut pieces = vec![];
+        let mut rest = *self;
+        let splitting_bits = self.floating & !other.floating;
+        for bit in (0..ADDRESS_BITS)
+            .map(|i| 1 << i)
+            .filter(|b| splitting_bits & b != 0)
+        {
+            rest.floating &= !bit;
+            pieces.push(AddressPattern {
+                fixed: rest.fixed | (!other.fixed & bit),
+                floating: rest.floating,
+            });
+            rest.fixed |= other.fixed & bit;
+        }
+        pieces
     }
 }
 
+// Keeps every write as a pattern of addresses, disjoint from the others, so
+// values can be summed without enumerating the addresses.
+#[derive(Default)]
+struct Memory {
+    regions: Vec<(AddressPattern, u64)>,
+}
 
-fn part1() {
-    let mut memory: HashMap<u64, u64> = HashMap::new();
-    let mut sum = 0u64;
-    let mut mask = (0u64, 0u64);
-    for command in io::stdin().lock().lines().map(|l| decoder_v1::parse_command(&l.unwrap())) {
-        match command {
-            decoder_v1::Command::Mem(addr, val) => {
-                let masked_val = (val & mask.0) | mask.1;
-                sum = sum + masked_val - memory.insert(addr, masked_val).unwrap_or(0u64);
+impl Memory {
+    fn write(&mut self, addresses: AddressPattern, value: u64) {
+        self.regions = self
+            .regions
+            .iter()
+            .flat_map(|&(region, v)| region.subtract(&addresses).into_iter().map(move |r| (r, v)))
+            .collect();
+        self.regions.push((addresses, value));
+    }
+
+    fn sum(&self) -> u64 {
+        self.regions
+            .iter()
+            .map(|(region, value)| region.len() * value)
+            .sum()
+    }
+}
+
+// Version 1 masks values, version 2 masks addresses
+fn decode_v1(mask: &Mask, address: u64, value: u64) -> (AddressPattern, u64) {
+    let masked_value = (value & !mask.zeros) | mask.ones;
+    (AddressPattern::exact(address), masked_value)
+}
+
+fn decode_v2(mask: &Mask, address: u64, value: u64) -> (AddressPattern, u64) {
+    let addresses = AddressPattern {
+        fixed: (address | mask.ones) & !mask.floating,
+        floating: mask.floating,
+    };
+    (addresses, value)
+}
+
+type Decoder = fn(&Mask, u64, u64) -> (AddressPattern, u64);
+
+fn execute(lines: impl Iterator<Item = String>, decode: Decoder) -> Memory {
+    let mut memory = Memory::default();
+    let mut mask = Mask::default();
+    for (i, line) in lines.enumerate() {
+        match line.parse::<Command>() {
+            Ok(Command::Mask(new_mask)) => mask = new_mask,
+            Ok(Command::Mem { address, value }) => {
+                let (addresses, value) = decode(&mask, address, value);
+                memory.write(addresses, value);
             }
-            decoder_v1::Command::Mask(m_and, m_or) => mask = (m_and, m_or)
+            Err(e) => panic!("line {}: {}", i + 1, e),
         }
     }
-    println!("{} is the sum of values in memory at the end", sum);
+    memory
+}
+
+fn run(decode: Decoder) {
+    let lines = io::stdin()
+        .lock()
+        .lines()
+        .map(|l| l.expect("Could not read line"));
+    let memory = execute(lines, decode);
+    println!("{} is the sum of values in memory at the end", memory.sum());
+}
+
+fn part1() {
+    run(decode_v1);
 }
 
 fn part2() {
-    let mut memory: HashMap<u64, u64> = HashMap::new();
-    let mut sum = 0u64;
-    let mut masks: Vec<(u64, u64)> = vec![];
-    for command in io::stdin().lock().lines().map(|l| decoder_v2::parse_command(&l.unwrap())) {
-        match command {
-            decoder_v2::Command::Mem(addr, val) => {
-                for mask in &masks {
-                    let masked_addr = (addr & mask.0) | mask.1;
-                    sum = sum + val - memory.insert(masked_addr, val).unwrap_or(0u64);
-                    // println!("wrote {} at memory address {}", val, masked_addr)
-                }
-            },
-            decoder_v2::Command::Mask(new_masks) => masks = new_masks
-        }
-    }
-    println!("{} is the sum of values in memory at the end", sum);
+    run(decode_v2);
 }
 
 pub fn main() {
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(),
         Some(p) if p == "part2" => part2(),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Every address matching the pattern, going through all the subsets of
+    // the floating bits
+    fn addresses(pattern: AddressPattern) -> Vec<u64> {
+        let mut subset = 0;
+        let mut addresses = vec![];
+        loop {
+            addresses.push(pattern.fixed | subset);
+            if subset == pattern.floating {
+                return addresses;
+            }
+            subset = (subset.wrapping_sub(pattern.floating)) & pattern.floating;
+        }
+    }
+
+    fn brute_force_sum(program: &str, decode: Decoder) -> u64 {
+        let mut memory = HashMap::new();
+        let mut mask = Mask::default();
+        for line in program.lines() {
+            match line.parse::<Command>().unwrap() {
+                Command::Mask(new_mask) => mask = new_mask,
+                Command::Mem { address, value } => {
+                    let (pattern, value) = decode(&mask, address, value);
+                    for address in addresses(pattern) {
+                        memory.insert(address, value);
+                    }
+                }
+            }
+        }
+        memory.values().sum()
+    }
+
+    fn sum(program: &str, decode: Decoder) -> u64 {
+        execute(program.lines().map(str::to_owned), decode).sum()
+    }
+
+    #[test]
+    fn test_examples() {
+        let example = include_str!("../../inputs/day14-example.in");
+        assert_eq!(sum(example, decode_v1), 165);
+        let example = include_str!("../../inputs/day14-example2.in");
+        assert_eq!(sum(example, decode_v2), 208);
+        assert_eq!(brute_force_sum(example, decode_v2), 208);
+    }
+
+    #[test]
+    fn test_overlapping_masks() {
+        let program = "\
+mask = 000000000000000000000000000000X1X0XX
+mem[8] = 3
+mask = 00000000000000000000000000000X0X1X0X
+mem[26] = 7
+mask = 0000000000000000000000000000000000XX
+mem[9] = 11
+mask = 000000000000000000000000000000X1X0XX
+mem[8] = 5
+mask = 00000000000000000000000000000000X000
+mem[3] = 2";
+        assert_eq!(sum(program, decode_v2), brute_force_sum(program, decode_v2));
+        assert_eq!(sum(program, decode_v1), brute_force_sum(program, decode_v1));
+    }
+
+    #[test]
+    fn test_random_masks_against_brute_force() {
+        // Masks floating over the low 10 bits, from a simple linear
+        // congruential generator so the test is deterministic
+        let mut seed: u64 = 42;
+        let mut random = move |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        let mut program = String::new();
+        for _ in 0..30 {
+            let low: String = (0..10)
+                .map(|_| ['0', '1', 'X'][random(3) as usize])
+                .collect();
+            program += &format!("mask = {}{}\n", "0".repeat(26), low);
+            for _ in 0..3 {
+                program += &format!("mem[{}] = {}\n", random(1024), random(1000));
+            }
+        }
+        assert_eq!(
+            sum(&program, decode_v2),
+            brute_force_sum(&program, decode_v2)
+        );
+    }
+}
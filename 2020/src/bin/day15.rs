@@ -1,38 +1,138 @@
 use std::collections::HashMap;
 use std::io::{self, BufRead};
 
-fn iter_game(initial_numbers: &[usize]) -> impl Iterator<Item = usize> {
-    // this assumes no duplicates between the initial numbers
-    let mut k: HashMap<usize, usize> = initial_numbers
-        .iter()
-        .enumerate()
-        .map(|(i, n)| (*n, i + 1))
-        .collect();
-    let mut next_spoken = 0;
-    let mut turn = initial_numbers.len() + 1;
-    let game_iterator = std::iter::repeat_with(move || {
-        next_spoken = match k.insert(next_spoken, turn) {
-            None => 0,
-            Some(n) => turn - n,
-        };
+// Remembers the last turn every number was spoken in
+trait Memory {
+    // Records that `n` was spoken at `turn`, returning the previous turn
+    fn remember(&mut self, n: usize, turn: u32) -> Option<u32>;
+}
+
+struct HashMemory(HashMap<usize, u32>);
+
+// Indexed by number, 0 meaning never spoken. Turns are 1-based, and the game
+// can't speak a number bigger than the number of turns, so once sized for the
+// turns and the seeds it never grows.
+struct FlatMemory(Vec<u32>);
+
+impl Memory for HashMemory {
+    fn remember(&mut self, n: usize, turn: u32) -> Option<u32> {
+        self.0.insert(n, turn)
+    }
+}
+
+impl Memory for FlatMemory {
+    fn remember(&mut self, n: usize, turn: u32) -> Option<u32> {
+        if n >= self.0.len() {
+            self.0.resize(n + 1, 0);
+        }
+        match std::mem::replace(&mut self.0[n], turn) {
+            0 => None,
+            previous => Some(previous),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Backend {
+    Hash,
+    Flat,
+}
+
+// Yields the spoken numbers turn by turn. Seeds go through the same memory as
+// the rest of the game, so repeated seeds are handled like any other number.
+fn iter_game<'a, M: Memory + 'a>(
+    initial_numbers: &'a [usize],
+    mut memory: M,
+) -> impl Iterator<Item = usize> + 'a {
+    let mut last: Option<usize> = None;
+    let mut turn: u32 = 0;
+    std::iter::repeat_with(move || {
         turn += 1;
-        next_spoken
-    });
-    initial_numbers
-        .to_owned()
-        .into_iter()
-        .chain(std::iter::once(next_spoken))
-        .chain(game_iterator)
+        let age = last.and_then(|n| memory.remember(n, turn - 1).map(|prev| turn - 1 - prev));
+        let spoken = match initial_numbers.get(turn as usize - 1) {
+            Some(&seed) => seed,
+            None => age.unwrap_or(0) as usize,
+        };
+        last = Some(spoken);
+        spoken
+    })
+}
+
+fn game<'a>(
+    initial_numbers: &'a [usize],
+    backend: Backend,
+    turns: usize,
+) -> Box<dyn Iterator<Item = usize> + 'a> {
+    let game: Box<dyn Iterator<Item = usize>> = match backend {
+        Backend::Hash => Box::new(iter_game(initial_numbers, HashMemory(HashMap::new()))),
+        Backend::Flat => {
+            let size = initial_numbers
+                .iter()
+                .max()
+                .map_or(0, |&n| n + 1)
+                .max(turns);
+            Box::new(iter_game(initial_numbers, FlatMemory(vec![0; size])))
+        }
+    };
+    Box::new(game.take(turns))
+}
+
+fn nth_spoken(initial_numbers: &[usize], backend: Backend, n: usize) -> usize {
+    game(initial_numbers, backend, n)
+        .last()
+        .expect("No turns played")
+}
+
+// Turns, among the first `turns`, in which `number` was spoken
+fn turn_history(
+    initial_numbers: &[usize],
+    backend: Backend,
+    turns: usize,
+    number: usize,
+) -> Vec<usize> {
+    game(initial_numbers, backend, turns)
+        .enumerate()
+        .filter(|&(_, spoken)| spoken == number)
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+fn backend() -> Backend {
+    if std::env::args().any(|arg| arg == "--hash") {
+        Backend::Hash
+    } else {
+        Backend::Flat
+    }
+}
+
+// With `--history N`, also list the turns in which N was spoken
+fn history_of() -> Option<usize> {
+    std::env::args()
+        .skip_while(|arg| arg != "--history")
+        .nth(1)
+        .map(|n| n.parse().expect("Invalid number"))
+}
+
+fn generic_solution(initial_numbers: &[usize], turns: usize) {
+    let res = nth_spoken(initial_numbers, backend(), turns);
+    println!("{} is the {}th spoken number", res, turns);
+    if let Some(number) = history_of() {
+        let history = turn_history(initial_numbers, backend(), turns, number);
+        println!(
+            "{} was spoken {} times, in turns {:?}",
+            number,
+            history.len(),
+            history
+        );
+    }
 }
 
 fn part1(initial_numbers: &[usize]) {
-    let res = iter_game(initial_numbers).nth(2019).unwrap();
-    println!("{} is the 2020th spoken number", res);
+    generic_solution(initial_numbers, 2020);
 }
 
 fn part2(initial_numbers: &[usize]) {
-    let res = iter_game(initial_numbers).nth(29_999_999).unwrap();
-    println!("{} is the 30,000,000th spoken number", res);
+    generic_solution(initial_numbers, 30_000_000);
 }
 
 pub fn main() {
@@ -45,9 +145,73 @@ pub fn main() {
         .split(',')
         .map(|s| s.parse::<usize>().unwrap())
         .collect();
-    match std::env::args().skip(1).next() {
+    match std::env::args().nth(1) {
         Some(p) if p == "part1" => part1(&initial_numbers),
         Some(p) if p == "part2" => part2(&initial_numbers),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKENDS: [Backend; 2] = [Backend::Hash, Backend::Flat];
+
+    fn first_spoken(initial_numbers: &[usize], backend: Backend, turns: usize) -> Vec<usize> {
+        game(initial_numbers, backend, turns).collect()
+    }
+
+    #[test]
+    fn test_examples() {
+        let examples = [
+            (vec![0, 3, 6], 436),
+            (vec![1, 3, 2], 1),
+            (vec![2, 1, 3], 10),
+            (vec![3, 1, 2], 1836),
+        ];
+        for backend in BACKENDS.iter().copied() {
+            assert_eq!(
+                first_spoken(&[0, 3, 6], backend, 10),
+                vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]
+            );
+            for (seeds, spoken) in &examples {
+                assert_eq!(nth_spoken(seeds, backend, 2020), *spoken);
+            }
+        }
+    }
+
+    #[test]
+    fn test_repeated_seeds() {
+        for backend in BACKENDS.iter().copied() {
+            assert_eq!(first_spoken(&[1, 1], backend, 10), vec![1; 10]);
+            assert_eq!(
+                first_spoken(&[0, 0], backend, 10),
+                vec![0, 0, 1, 0, 2, 0, 2, 2, 1, 6]
+            );
+            assert_eq!(
+                first_spoken(&[3, 0, 3], backend, 12),
+                vec![3, 0, 3, 2, 0, 3, 3, 1, 0, 4, 0, 2]
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        for seeds in [vec![0, 3, 6], vec![1, 1], vec![7, 0, 7, 12], vec![20]].iter() {
+            assert_eq!(
+                first_spoken(seeds, Backend::Hash, 5000),
+                first_spoken(seeds, Backend::Flat, 5000)
+            );
+        }
+    }
+
+    #[test]
+    fn test_turn_history() {
+        for backend in BACKENDS.iter().copied() {
+            assert_eq!(turn_history(&[0, 3, 6], backend, 10, 0), vec![1, 4, 8, 10]);
+            assert_eq!(turn_history(&[0, 3, 6], backend, 10, 6), vec![3]);
+            assert!(turn_history(&[0, 3, 6], backend, 10, 5).is_empty());
+        }
+    }
+}
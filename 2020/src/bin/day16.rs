@@ -1,145 +1,224 @@
-use itertools::Itertools;
-use std::collections::HashSet;
+use aoc2020::matching::{alternative_edges, hopcroft_karp};
 use std::fmt;
-use std::io::{self, BufRead};
+use std::io::{self, Read};
 use std::ops::RangeInclusive;
+use std::str::FromStr;
 
 type Ticket = Vec<usize>;
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq)]
 struct Field {
     name: String,
     rules: Vec<RangeInclusive<usize>>,
 }
 
+struct Notes {
+    fields: Vec<Field>,
+    my_ticket: Ticket,
+    nearby_tickets: Vec<Ticket>,
+}
+
+// Why the positions on the tickets can't be told apart
+enum AssignmentError {
+    // Positions no field fits, once the others are assigned
+    Impossible(Vec<usize>),
+    // Positions that could be more than one field: (position, field)
+    Ambiguous(Vec<(usize, usize)>),
+}
+
 impl fmt::Display for Field {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)
     }
 }
 
-fn parse_field(line: &str) -> Field {
-    let (name, rules) = line.splitn(2, ": ").collect_tuple().unwrap();
-    let rules = rules
-        .split(" or ")
-        .map(|r| {
-            let (min, max) = r
-                .split("-")
-                .map(|n| n.parse::<usize>().unwrap())
-                .collect_tuple()
-                .unwrap();
-            min..=max
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut it = line.splitn(2, ": ");
+        let (name, rules) = match (it.next(), it.next()) {
+            (Some(name), Some(rules)) => (name, rules),
+            _ => return Err(format!("expected a field rule: {}", line)),
+        };
+        let rules = rules
+            .split(" or ")
+            .map(|rule| {
+                let mut bounds = rule.splitn(2, '-').map(|n| n.parse::<usize>());
+                match (bounds.next(), bounds.next()) {
+                    (Some(Ok(min)), Some(Ok(max))) => Ok(min..=max),
+                    _ => Err(format!("invalid range {}", rule)),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Field {
+            name: name.to_owned(),
+            rules,
         })
-        .collect_vec();
-    Field {
-        name: name.to_owned(),
-        rules: rules.to_owned(),
     }
 }
 
-fn parse_ticket(line: &str) -> Ticket {
-    line.trim()
-        .split(',')
-        .map(|n| n.parse::<usize>().unwrap())
-        .collect_vec()
+fn parse_ticket(line: &str) -> Result<Ticket, String> {
+    line.split(',')
+        .map(|n| n.parse().map_err(|_| format!("invalid ticket value {}", n)))
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Fields,
+    MyTicket,
+    NearbyTickets,
 }
 
-fn read_input() -> (Vec<Field>, Ticket, Vec<Ticket>) {
-    let stdin = io::stdin();
-    let fields = stdin
-        .lock()
-        .lines()
-        .map(|l| l.unwrap())
-        .take_while(|l| l.trim().len() > 0)
-        .map(|l| parse_field(&l))
-        .collect_vec();
-
-    let mut buf = String::new();
-    stdin.read_line(&mut buf).unwrap();
-    buf.clear();
-
-    stdin.read_line(&mut buf).unwrap();
-    let my_ticket = parse_ticket(buf.as_str());
-
-    stdin.read_line(&mut buf).unwrap();
-    stdin.read_line(&mut buf).unwrap();
-    let tickets = stdin
-        .lock()
-        .lines()
-        .map(|l| parse_ticket(&l.unwrap()))
-        .collect_vec();
-
-    (fields, my_ticket, tickets)
+// Sections are told apart by their headers, so blank lines can go anywhere
+impl FromStr for Notes {
+    type Err = String;
+
+    fn from_str(notes: &str) -> Result<Self, Self::Err> {
+        let mut section = Section::Fields;
+        let mut fields = vec![];
+        let mut my_ticket = None;
+        let mut nearby_tickets = vec![];
+        for (i, line) in notes.lines().enumerate() {
+            let line = line.trim();
+            let parsed = match (line, section) {
+                ("", _) => Ok(()),
+                ("your ticket:", Section::Fields) => {
+                    section = Section::MyTicket;
+                    Ok(())
+                }
+                ("nearby tickets:", Section::MyTicket) => {
+                    section = Section::NearbyTickets;
+                    Ok(())
+                }
+                (_, Section::Fields) => line.parse().map(|field| fields.push(field)),
+                (_, Section::MyTicket) if my_ticket.is_some() => {
+                    Err("more than one ticket of mine".to_owned())
+                }
+                (_, Section::MyTicket) => parse_ticket(line).map(|t| my_ticket = Some(t)),
+                (_, Section::NearbyTickets) => parse_ticket(line).map(|t| nearby_tickets.push(t)),
+            };
+            parsed.map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        let my_ticket = my_ticket.ok_or("missing my ticket")?;
+        if let Some(ticket) = std::iter::once(&my_ticket)
+            .chain(&nearby_tickets)
+            .find(|t| t.len() != fields.len())
+        {
+            return Err(format!(
+                "ticket {:?} doesn't have {} values",
+                ticket,
+                fields.len()
+            ));
+        }
+        Ok(Notes {
+            fields,
+            my_ticket,
+            nearby_tickets,
+        })
+    }
 }
 
-fn within_constraints(field: &Field, value: usize) -> bool {
-    field.rules.iter().any(|rule| rule.contains(&value))
+impl Field {
+    fn allows(&self, value: usize) -> bool {
+        self.rules.iter().any(|rule| rule.contains(&value))
+    }
 }
 
-fn part1(fields: &[Field], tickets: &[Ticket]) {
-    let mut valid: Vec<bool> = vec![false; 1000];
-    fields
-        .iter()
-        .flat_map(|field| &field.rules)
-        .flat_map(|x| x.to_owned())
-        .for_each(|v| valid[v] = true);
-    let error_rate: usize = tickets
+impl Notes {
+    fn is_valid_value(&self, value: usize) -> bool {
+        self.fields.iter().any(|field| field.allows(value))
+    }
+
+    fn valid_tickets(&self) -> impl Iterator<Item = &Ticket> {
+        std::iter::once(&self.my_ticket)
+            .chain(&self.nearby_tickets)
+            .filter(move |ticket| ticket.iter().all(|&v| self.is_valid_value(v)))
+    }
+
+    // For every position, the fields its values all fit
+    fn candidates(&self) -> Vec<Vec<usize>> {
+        (0..self.fields.len())
+            .map(|position| {
+                (0..self.fields.len())
+                    .filter(|&f| {
+                        self.valid_tickets()
+                            .all(|ticket| self.fields[f].allows(ticket[position]))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The field at every position, if there's exactly one way to pick them
+    fn assign_fields(&self) -> Result<Vec<&Field>, AssignmentError> {
+        let candidates = self.candidates();
+        let matching = hopcroft_karp(&candidates, self.fields.len());
+        if !matching.is_perfect() {
+            return Err(AssignmentError::Impossible(matching.unmatched_left()));
+        }
+        let alternatives = alternative_edges(&candidates, &matching);
+        if !alternatives.is_empty() {
+            return Err(AssignmentError::Ambiguous(alternatives));
+        }
+        Ok(matching
+            .left
+            .iter()
+            .map(|f| &self.fields[f.unwrap()])
+            .collect())
+    }
+}
+
+fn read_notes() -> Notes {
+    let mut buffer = String::new();
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .expect("Could not read from stdin!");
+    buffer
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid notes: {}", e))
+}
+
+fn part1(notes: &Notes) {
+    let error_rate: usize = notes
+        .nearby_tickets
         .iter()
-        .flat_map(|t| t.iter())
-        .filter(|v| !valid[**v])
+        .flatten()
+        .filter(|&&v| !notes.is_valid_value(v))
         .sum();
     println!("Ticket scanning error rate: {}", error_rate);
 }
 
-fn part2(fields: &Vec<Field>, my_ticket: &Ticket, nearby_tickets: &Vec<Ticket>) {
-    let mut valid: Vec<bool> = vec![false; 1000];
-    fields
-        .iter()
-        .flat_map(|field| &field.rules)
-        .flat_map(|x| x.to_owned())
-        .for_each(|v| valid[v] = true);
-    let k = std::iter::once(my_ticket)
-        .chain(nearby_tickets)
-        .filter(|ticket| ticket.iter().all(|val| valid[*val]))
-        .flat_map(|ticket| ticket.iter().enumerate())
-        .sorted()
-        .group_by(|x| x.0)
-        .into_iter()
-        .map(|(_index, group)| {
-            let mut possible_fields: HashSet<&Field> = fields.iter().collect();
-            for (_, val) in group {
-                for field in possible_fields.to_owned() {
-                    if !within_constraints(field, *val) {
-                        possible_fields.remove(field);
-                    }
+fn part2(notes: &Notes) {
+    match notes.assign_fields() {
+        Ok(fields) => {
+            let mut res = 1;
+            for (position, field) in fields.iter().enumerate() {
+                if field.name.starts_with("departure") {
+                    res *= notes.my_ticket[position];
                 }
+                println!("field {} is {}", position, field);
+            }
+            println!("Result is {}", res);
+        }
+        Err(AssignmentError::Impossible(positions)) => {
+            println!("No field assignment fits positions {:?}", positions)
+        }
+        Err(AssignmentError::Ambiguous(alternatives)) => {
+            println!("The field assignment is ambiguous:");
+            for (position, field) in alternatives {
+                println!("  field {} could be {}", position, notes.fields[field]);
             }
-            possible_fields
-        })
-        .collect_vec();
-    let mut used_fields: HashSet<&Field> = HashSet::new();
-    let sorted_iterator = k.iter().enumerate().sorted_by_key(|(_i, s)| s.len());
-    let mut res = 1;
-    for (field_index, possible_fields) in sorted_iterator {
-        let (field,) = possible_fields
-            .difference(&used_fields)
-            .copied()
-            .collect_tuple()
-            .unwrap();
-        used_fields.insert(&field);
-        if field.name.starts_with("departure") {
-            res *= my_ticket[field_index];
         }
-        println!("field {} is {}", field_index, field);
     }
-    println!("Result is {}", res);
 }
 
 pub fn main() {
-    let (fields, my_ticket, nearby_tickets) = read_input();
-    match std::env::args().skip(1).next() {
-        Some(p) if p == "part1" => part1(&fields, &nearby_tickets),
-        Some(p) if p == "part2" => part2(&fields, &my_ticket, &nearby_tickets),
+    let notes = read_notes();
+    match std::env::args().nth(1) {
+        Some(p) if p == "part1" => part1(&notes),
+        Some(p) if p == "part2" => part2(&notes),
         _ => println!("Please specify a part (part1 | part2)"),
     }
 }
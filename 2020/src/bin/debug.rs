@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+
+use aoc2020::arch::*;
+
+const HELP: &str = "\
+Commands:
+  s, step [N]       execute the next N instructions (default 1)
+  r, rstep [N]      undo the last N executed instructions (default 1)
+  c, continue       run until a breakpoint, a watch, a loop or the end
+  b, break N        set a breakpoint on instruction N
+  d, delete N       remove the breakpoint on instruction N
+  w, watch          toggle stopping whenever the accumulator changes
+  l, list [N [M]]   disassemble instructions N to M, with visit counts
+  p, print          show instruction pointer and registers
+  h, help           show this message
+  q, quit           exit the debugger";
+
+// The most instructions a single `step` runs, since every one of them is
+// kept in the history
+const MAX_STEPS: usize = 1_000_000;
+
+// Why `continue` gave control back to the user
+#[derive(Debug, PartialEq, Eq)]
+enum Stop {
+    Breakpoint(usize),
+    AccChanged { from: isize, to: isize },
+    Loop(usize),
+    Exited(Outcome),
+}
+
+struct Debugger<'a> {
+    machine: Machine<'a>,
+    breakpoints: HashSet<usize>,
+    watch_acc: bool,
+    visits: Vec<usize>,
+    // Instruction pointer and registers before every executed instruction
+    history: Vec<(isize, Vec<isize>)>,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(program: &'a Program) -> Self {
+        Self {
+            machine: Machine::new(&HANDHELD, program),
+            breakpoints: HashSet::new(),
+            watch_acc: false,
+            visits: vec![0; program.len()],
+            history: vec![],
+        }
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        match self.machine.current() {
+            Some(_) => None,
+            None if self.machine.ip == self.machine.program().len() as isize => {
+                Some(Outcome::Terminated {
+                    acc: self.machine.acc(),
+                })
+            }
+            None => Some(Outcome::JumpedOutOfBounds {
+                target: self.machine.ip,
+            }),
+        }
+    }
+
+    fn step(&mut self) -> Option<usize> {
+        let snapshot = (self.machine.ip, self.machine.registers.clone());
+        let executed = self.machine.step()?;
+        self.visits[executed] += 1;
+        self.history.push(snapshot);
+        Some(executed)
+    }
+
+    fn reverse_step(&mut self) -> Option<usize> {
+        let (ip, registers) = self.history.pop()?;
+        self.machine.ip = ip;
+        self.machine.registers = registers;
+        self.visits[ip as usize] -= 1;
+        Some(ip as usize)
+    }
+
+    // A loop is an instruction about to run a second time during this
+    // `continue`, not just one run at some point earlier in the session
+    fn resume(&mut self) -> Stop {
+        let mut first = true;
+        let mut visited = HashSet::new();
+        loop {
+            if let Some(outcome) = self.outcome() {
+                return Stop::Exited(outcome);
+            }
+            let ip = self.machine.ip as usize;
+            // Don't stop again where the previous command left us
+            if !first && self.breakpoints.contains(&ip) {
+                return Stop::Breakpoint(ip);
+            }
+            if !visited.insert(ip) {
+                return Stop::Loop(ip);
+            }
+            first = false;
+            let before = self.machine.acc();
+            self.step();
+            if self.watch_acc && self.machine.acc() != before {
+                return Stop::AccChanged {
+                    from: before,
+                    to: self.machine.acc(),
+                };
+            }
+        }
+    }
+
+    fn list(&self, from: usize, to: usize) {
+        let program = self.machine.program();
+        for (index, instr) in program.iter().enumerate().take(to).skip(from) {
+            println!(
+                "{}{} {:>5} [{:>3}]  {}",
+                if self.machine.ip == index as isize {
+                    '>'
+                } else {
+                    ' '
+                },
+                if self.breakpoints.contains(&index) {
+                    '*'
+                } else {
+                    ' '
+                },
+                index,
+                self.visits[index],
+                instr
+            );
+        }
+    }
+
+    fn print_state(&self) {
+        if self.machine.current().is_some() {
+            let ip = self.machine.ip as usize;
+            self.list(ip, ip + 1);
+        }
+        println!(
+            "ip = {}, registers = {:?}, {} steps executed",
+            self.machine.ip,
+            self.machine.registers,
+            self.history.len()
+        );
+        if let Some(outcome) = self.outcome() {
+            println!("program has exited: {:?}", outcome);
+        }
+    }
+}
+
+fn parse_arg(arg: Option<&str>, default: usize) -> Result<usize, String> {
+    match arg {
+        None => Ok(default),
+        Some(a) => a.parse().map_err(|_| format!("invalid number {}", a)),
+    }
+}
+
+fn execute(debugger: &mut Debugger, line: &str) -> Result<bool, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(true),
+    };
+    let arg = words.next();
+    match command {
+        "s" | "step" => {
+            let n = parse_arg(arg, 1)?;
+            if n > MAX_STEPS {
+                return Err(format!(
+                    "can step at most {} instructions at once",
+                    MAX_STEPS
+                ));
+            }
+            for _ in 0..n {
+                if debugger.step().is_none() {
+                    break;
+                }
+            }
+            debugger.print_state();
+        }
+        "r" | "rstep" => {
+            for _ in 0..parse_arg(arg, 1)? {
+                if debugger.reverse_step().is_none() {
+                    println!("at the beginning of the history");
+                    break;
+                }
+            }
+            debugger.print_state();
+        }
+        "c" | "continue" => {
+            match debugger.resume() {
+                Stop::Breakpoint(index) => println!("breakpoint at {}", index),
+                Stop::AccChanged { from, to } => println!("accumulator changed {} -> {}", from, to),
+                Stop::Loop(index) => println!("instruction {} is about to run again: loop", index),
+                Stop::Exited(outcome) => println!("program exited: {:?}", outcome),
+            }
+            debugger.print_state();
+        }
+        "b" | "break" => {
+            let index = parse_arg(arg, debugger.machine.ip.max(0) as usize)?;
+            debugger.breakpoints.insert(index);
+            println!("breakpoint set at {}", index);
+        }
+        "d" | "delete" => {
+            let index = parse_arg(arg, debugger.machine.ip.max(0) as usize)?;
+            if !debugger.breakpoints.remove(&index) {
+                return Err(format!("no breakpoint at {}", index));
+            }
+        }
+        "w" | "watch" => {
+            debugger.watch_acc = !debugger.watch_acc;
+            println!(
+                "accumulator watch {}",
+                if debugger.watch_acc { "on" } else { "off" }
+            );
+        }
+        "l" | "list" => {
+            let from = parse_arg(arg, 0)?;
+            let to = parse_arg(
+                words.next(),
+                debugger.machine.program().len().saturating_sub(1),
+            )?;
+            debugger.list(from, to.saturating_add(1));
+        }
+        "p" | "print" => debugger.print_state(),
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(false),
+        _ => return Err(format!("unknown command {}, try help", command)),
+    }
+    Ok(true)
+}
+
+pub fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Please specify the program to debug");
+            return;
+        }
+    };
+    let source = std::fs::read_to_string(&path).expect("Could not read program");
+    let program = match parse_program(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return;
+        }
+    };
+    let mut debugger = Debugger::new(&program);
+    println!(
+        "{} instructions loaded, type help for the commands",
+        program.len()
+    );
+    let stdin = io::stdin();
+    loop {
+        print!("(debug) ");
+        io::stdout().flush().expect("Could not write to stdout");
+        let mut line = String::new();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .expect("Could not read command")
+            == 0
+        {
+            break;
+        }
+        match execute(&mut debugger, &line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../../inputs/day8-example.in");
+
+    #[test]
+    fn test_continue() {
+        let program = parse_program(EXAMPLE).unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(debugger.resume(), Stop::Loop(1));
+        assert_eq!(debugger.history.len(), 7);
+        // Instructions run before this `continue` don't count as a loop
+        debugger.breakpoints.insert(6);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(6));
+        assert_eq!(debugger.resume(), Stop::Breakpoint(6));
+        debugger.breakpoints.clear();
+        assert_eq!(debugger.resume(), Stop::Loop(6));
+        assert_eq!(debugger.machine.acc(), 16);
+    }
+
+    #[test]
+    fn test_watch() {
+        let program = parse_program(EXAMPLE).unwrap();
+        let mut debugger = Debugger::new(&program);
+        debugger.watch_acc = true;
+        assert_eq!(debugger.resume(), Stop::AccChanged { from: 0, to: 1 });
+        assert_eq!(debugger.machine.ip, 2);
+    }
+
+    #[test]
+    fn test_reverse_step() {
+        let program = parse_program(EXAMPLE).unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(execute(&mut debugger, "s 4"), Ok(true));
+        assert_eq!(debugger.machine.ip, 7);
+        assert_eq!(debugger.machine.acc(), 2);
+        assert_eq!(debugger.reverse_step(), Some(6));
+        assert_eq!(execute(&mut debugger, "r 4"), Ok(true));
+        assert_eq!(debugger.machine.ip, 0);
+        assert_eq!(debugger.machine.acc(), 0);
+        assert!(debugger.visits.iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_exit() {
+        let program = parse_program("acc +3\njmp -5\n").unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Exited(Outcome::JumpedOutOfBounds { target: -4 })
+        );
+        let program = parse_program("acc +3\nnop +0\n").unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Exited(Outcome::Terminated { acc: 3 })
+        );
+    }
+
+    #[test]
+    fn test_commands() {
+        let program = parse_program(EXAMPLE).unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert!(execute(&mut debugger, "s 99999999999").is_err());
+        assert_eq!(debugger.history.len(), 0);
+        assert!(execute(&mut debugger, "d 3").is_err());
+        assert!(execute(&mut debugger, "frobnicate").is_err());
+        assert_eq!(execute(&mut debugger, "b 3"), Ok(true));
+        assert!(debugger.breakpoints.contains(&3));
+        assert_eq!(execute(&mut debugger, "q"), Ok(false));
+    }
+}
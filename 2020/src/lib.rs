@@ -1,2 +1,6 @@
 pub mod arch;
+pub mod automaton;
+pub mod matching;
+pub mod number_theory;
 pub mod utils;
+pub mod window;
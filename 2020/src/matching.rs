@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+
+// A matching in a bipartite graph whose left vertices are 0..adjacency.len()
+// and right vertices 0..right_len
+pub struct Matching {
+    pub left: Vec<Option<usize>>,
+    pub right: Vec<Option<usize>>,
+}
+
+impl Matching {
+    pub fn len(&self) -> usize {
+        self.left.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.left.iter().chain(&self.right).all(Option::is_some)
+    }
+
+    pub fn unmatched_left(&self) -> Vec<usize> {
+        unmatched(&self.left)
+    }
+
+    pub fn unmatched_right(&self) -> Vec<usize> {
+        unmatched(&self.right)
+    }
+}
+
+fn unmatched(side: &[Option<usize>]) -> Vec<usize> {
+    side.iter()
+        .enumerate()
+        .filter(|(_, mate)| mate.is_none())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Maximum matching with Hopcroft-Karp: every phase finds the shortest
+// augmenting paths with a BFS from the free left vertices, then augments
+// along as many vertex-disjoint ones as a layered DFS finds. O(E sqrt(V)).
+pub fn hopcroft_karp(adjacency: &[Vec<usize>], right_len: usize) -> Matching {
+    let mut matching = Matching {
+        left: vec![None; adjacency.len()],
+        right: vec![None; right_len],
+    };
+    loop {
+        let mut layers = match bfs_layers(adjacency, &matching) {
+            Some(layers) => layers,
+            None => return matching,
+        };
+        for l in 0..adjacency.len() {
+            if matching.left[l].is_none() {
+                augment(adjacency, &mut matching, &mut layers, l);
+            }
+        }
+    }
+}
+
+// Distance of every left vertex from the free ones, or None when there's no
+// augmenting path left
+fn bfs_layers(adjacency: &[Vec<usize>], matching: &Matching) -> Option<Vec<Option<usize>>> {
+    let mut layers = vec![None; adjacency.len()];
+    let mut queue = VecDeque::new();
+    for l in matching.unmatched_left() {
+        layers[l] = Some(0);
+        queue.push_back(l);
+    }
+    let mut found = false;
+    while let Some(l) = queue.pop_front() {
+        let depth = layers[l].unwrap();
+        for &r in &adjacency[l] {
+            match matching.right[r] {
+                None => found = true,
+                Some(mate) if layers[mate].is_none() => {
+                    layers[mate] = Some(depth + 1);
+                    queue.push_back(mate);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    if found {
+        Some(layers)
+    } else {
+        None
+    }
+}
+
+fn augment(
+    adjacency: &[Vec<usize>],
+    matching: &mut Matching,
+    layers: &mut [Option<usize>],
+    l: usize,
+) -> bool {
+    let depth = layers[l];
+    for &r in &adjacency[l] {
+        let next = match matching.right[r] {
+            None => true,
+            Some(mate) => {
+                layers[mate] == depth.map(|d| d + 1) && augment(adjacency, matching, layers, mate)
+            }
+        };
+        if next {
+            matching.left[l] = Some(r);
+            matching.right[r] = Some(l);
+            return true;
+        }
+    }
+    // Dead end, don't visit it again in this phase
+    layers[l] = None;
+    false
+}
+
+// Edges outside a perfect matching that belong to some other perfect
+// matching. An edge (l, r) does when there's an alternating cycle through
+// it: from r to its mate, along unmatched edges to right vertices and back
+// through their mates, until reaching l.
+pub fn alternative_edges(adjacency: &[Vec<usize>], matching: &Matching) -> Vec<(usize, usize)> {
+    let mut alternatives = vec![];
+    for (l, edges) in adjacency.iter().enumerate() {
+        for &r in edges {
+            if matching.left[l] != Some(r) && closes_cycle(adjacency, matching, l, r) {
+                alternatives.push((l, r));
+            }
+        }
+    }
+    alternatives
+}
+
+fn closes_cycle(adjacency: &[Vec<usize>], matching: &Matching, l: usize, r: usize) -> bool {
+    let mut visited = vec![false; adjacency.len()];
+    let mut stack: Vec<usize> = matching.right[r].into_iter().collect();
+    while let Some(current) = stack.pop() {
+        if current == l {
+            return true;
+        }
+        if std::mem::replace(&mut visited[current], true) {
+            continue;
+        }
+        stack.extend(
+            adjacency[current]
+                .iter()
+                .filter(|&&next| matching.left[current] != Some(next))
+                .filter_map(|&next| matching.right[next]),
+        );
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_perfect_matching() {
+        let adjacency = vec![vec![0, 1, 2], vec![1], vec![1, 2]];
+        let matching = hopcroft_karp(&adjacency, 3);
+        assert!(matching.is_perfect());
+        assert_eq!(matching.left, vec![Some(0), Some(1), Some(2)]);
+        assert!(alternative_edges(&adjacency, &matching).is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_and_impossible_matchings() {
+        let adjacency = vec![vec![0, 1], vec![0, 1], vec![2]];
+        let matching = hopcroft_karp(&adjacency, 3);
+        assert!(matching.is_perfect());
+        let mut alternatives = alternative_edges(&adjacency, &matching);
+        alternatives.sort_unstable();
+        assert_eq!(alternatives.len(), 2);
+        assert!(alternatives.iter().all(|&(l, _)| l < 2));
+
+        let adjacency = vec![vec![0], vec![0], vec![1, 2]];
+        let matching = hopcroft_karp(&adjacency, 3);
+        assert_eq!(matching.len(), 2);
+        assert_eq!(matching.unmatched_left().len(), 1);
+        assert_eq!(matching.unmatched_right().len(), 1);
+    }
+}
@@ -0,0 +1,84 @@
+use std::convert::TryFrom;
+
+// Returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+pub fn gcd(a: i64, b: i64) -> i64 {
+    extended_gcd(a, b).0
+}
+
+// The x in 0..m such that a * x = 1 (mod m), if a and m are coprime
+pub fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    match extended_gcd(a.rem_euclid(m), m) {
+        (1, x, _) => Some(x.rem_euclid(m)),
+        _ => None,
+    }
+}
+
+// Solves the system x = residue (mod modulus) for all the given congruences.
+// Moduli don't need to be coprime: the solution, if the system is consistent,
+// is returned as (x, lcm of the moduli) with x in 0..lcm. Moduli must be
+// positive, otherwise there's no solution, and there's none either when the
+// lcm doesn't fit in an i64.
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    congruences
+        .iter()
+        .try_fold((0, 1), |(x, m), &(residue, modulus)| {
+            if modulus <= 0 {
+                return None;
+            }
+            let (g, p, _) = extended_gcd(m, modulus);
+            let (g, diff) = (g as i128, residue as i128 - x as i128);
+            if diff % g != 0 {
+                return None;
+            }
+            // x + m * k = residue (mod modulus), so k = diff / g * p (mod modulus / g)
+            let step = modulus as i128 / g;
+            let k = diff / g * p as i128 % step;
+            let lcm = m as i128 * step;
+            let combined = (x as i128 + m as i128 * k).rem_euclid(lcm);
+            Some((i64::try_from(combined).ok()?, i64::try_from(lcm).ok()?))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(0, 4), (1, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(3, 0)]), None);
+        assert_eq!(crt(&[(2, 3), (1, -5)]), None);
+        // The lcm is bigger than i64::MAX
+        let big = [(1, 3037000493), (2, 3037000499), (3, 3037000453)];
+        assert_eq!(crt(&big), None);
+        assert!(crt(&big[..2]).is_some());
+        assert_eq!(crt(&[(1, 5), (i64::MIN, 7)]), Some((6, 35)));
+    }
+}
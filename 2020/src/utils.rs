@@ -1,19 +1,146 @@
-use multiset::HashMultiSet;
-use std::cmp::{Eq, PartialOrd};
-use std::hash::Hash;
-use std::ops::{Add, Sub};
+use std::cmp::Ordering;
+use std::ops::Add;
 
 pub fn find_couple_with_sum<T>(numbers: &[T], sum: T) -> Option<(T, T)>
 where
-    T: Sub<Output = T> + Add<Output = T> + Copy + Eq + PartialOrd + Hash,
+    T: Add<Output = T> + Copy + Ord,
 {
-    let number_set: HashMultiSet<&T> = numbers.into_iter().collect();
-    numbers.iter().filter(|&n1| *n1 <= sum).find_map(|&n1| {
-        let n2 = sum - n1;
-        match number_set.count_of(&&n2) {
-            c if n1 != n2 && c >= 1 => Some((n1, n2)),
-            c if c >= 2 => Some((n1, n2)),
-            _ => None,
+    find_k_with_sum(numbers, 2, sum).map(|couple| (couple[0], couple[1]))
+}
+
+// First k numbers (in increasing order) that add up to the target. Every
+// number can be used as many times as it appears in the input.
+pub fn find_k_with_sum<T>(numbers: &[T], k: usize, target: T) -> Option<Vec<T>>
+where
+    T: Add<Output = T> + Copy + Ord,
+{
+    let mut solution = None;
+    k_sum(numbers, k, target, &mut |found| {
+        solution = Some(found.to_vec());
+        false
+    });
+    solution
+}
+
+// All the distinct ways of picking k numbers that add up to the target
+pub fn find_all_k_with_sum<T>(numbers: &[T], k: usize, target: T) -> Vec<Vec<T>>
+where
+    T: Add<Output = T> + Copy + Ord,
+{
+    let mut solutions = vec![];
+    k_sum(numbers, k, target, &mut |found| {
+        solutions.push(found.to_vec());
+        true
+    });
+    solutions
+}
+
+// Sorts the numbers, fixes all but the last two and finds those with two
+// pointers, so it's O(n^(k-1)). Only ever adds numbers, never subtracts them,
+// so it works for unsigned types too. `found` returns whether to keep going.
+fn k_sum<T>(numbers: &[T], k: usize, target: T, found: &mut dyn FnMut(&[T]) -> bool)
+where
+    T: Add<Output = T> + Copy + Ord,
+{
+    let mut sorted = numbers.to_vec();
+    sorted.sort_unstable();
+    let mut chosen = Vec::with_capacity(k);
+    if k > 0 {
+        k_sum_from(&sorted, k, None, target, &mut chosen, found);
+    }
+}
+
+fn k_sum_from<T>(
+    sorted: &[T],
+    k: usize,
+    partial: Option<T>,
+    target: T,
+    chosen: &mut Vec<T>,
+    found: &mut dyn FnMut(&[T]) -> bool,
+) -> bool
+where
+    T: Add<Output = T> + Copy + Ord,
+{
+    let plus = |n: T| partial.map_or(n, |p| p + n);
+    if sorted.len() < k {
+        return true;
+    }
+    match k {
+        1 => {
+            if let Ok(i) = sorted.binary_search_by(|&n| plus(n).cmp(&target)) {
+                chosen.push(sorted[i]);
+                let keep_going = found(chosen);
+                chosen.pop();
+                return keep_going;
+            }
+            true
         }
-    })
+        2 => {
+            let (mut lo, mut hi) = (0, sorted.len() - 1);
+            while lo < hi {
+                match (plus(sorted[lo]) + sorted[hi]).cmp(&target) {
+                    Ordering::Less => lo += 1,
+                    Ordering::Greater => hi -= 1,
+                    Ordering::Equal => {
+                        chosen.extend([sorted[lo], sorted[hi]].iter());
+                        let keep_going = found(chosen);
+                        chosen.truncate(chosen.len() - 2);
+                        if !keep_going {
+                            return false;
+                        }
+                        let (low, high) = (sorted[lo], sorted[hi]);
+                        while lo < hi && sorted[lo] == low {
+                            lo += 1;
+                        }
+                        while lo < hi && sorted[hi] == high {
+                            hi -= 1;
+                        }
+                    }
+                }
+            }
+            true
+        }
+        _ => {
+            for i in 0..=sorted.len() - k {
+                if i > 0 && sorted[i] == sorted[i - 1] {
+                    continue;
+                }
+                chosen.push(sorted[i]);
+                let keep_going = k_sum_from(
+                    &sorted[i + 1..],
+                    k - 1,
+                    Some(plus(sorted[i])),
+                    target,
+                    chosen,
+                    found,
+                );
+                chosen.pop();
+                if !keep_going {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_k_sum_multiplicities() {
+        let numbers = [1010, 3, 1010, 7, 1000, 10];
+        assert_eq!(find_k_with_sum(&numbers, 2, 2020), Some(vec![1010, 1010]));
+        assert_eq!(find_k_with_sum(&[1010, 3], 2, 2020), None);
+        assert_eq!(
+            find_all_k_with_sum(&numbers, 3, 2020),
+            vec![vec![10, 1000, 1010]]
+        );
+        assert_eq!(find_k_with_sum(&[5u64, 1, 2], 4, 8), None);
+        assert_eq!(
+            find_all_k_with_sum(&[-2, -1, 0, 1, 2, 3], 4, 0),
+            vec![vec![-2, -1, 0, 3], vec![-2, -1, 1, 2]]
+        );
+    }
 }
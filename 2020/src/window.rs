@@ -0,0 +1,96 @@
+use multiset::HashMultiSet;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Add, Neg, Range, Sub};
+
+// Position and value of the first number, after the first k, that isn't the
+// sum of two of the k numbers preceding it. The window is kept
+// in a multiset updated one number at a time, so each step is O(k). Only
+// for signed types, since complements of the target can be negative.
+pub fn first_not_pair_sum<T>(numbers: &[T], k: usize) -> Option<(usize, T)>
+where
+    T: Sub<Output = T> + Neg<Output = T> + Copy + Eq + Hash,
+{
+    let mut window: HashMultiSet<T> = numbers.iter().take(k).copied().collect();
+    for i in k..numbers.len() {
+        let target = numbers[i];
+        let is_pair_sum = numbers[i - k..i].iter().any(|&n| {
+            let complement = target - n;
+            match window.count_of(&complement) {
+                c if complement != n => c >= 1,
+                c => c >= 2,
+            }
+        });
+        if !is_pair_sum {
+            return Some((i, target));
+        }
+        window.remove(&numbers[i - k]);
+        window.insert(target);
+    }
+    None
+}
+
+// First range of at least `min_len` consecutive numbers adding up to the
+// target. Looks up earlier prefix sums in a hash map instead of moving two
+// pointers, so it works with negative numbers too (and only with signed
+// types, as prefix sums minus the target can be negative).
+pub fn contiguous_sum<T>(numbers: &[T], target: T, min_len: usize) -> Option<Range<usize>>
+where
+    T: Add<Output = T> + Sub<Output = T> + Neg<Output = T> + Copy + Default + Eq + Hash,
+{
+    let prefix_sums: Vec<T> = std::iter::once(T::default())
+        .chain(numbers.iter().scan(T::default(), |sum, &n| {
+            *sum = *sum + n;
+            Some(*sum)
+        }))
+        .collect();
+    let min_len = min_len.max(1);
+    // Earliest position of every prefix sum that can start a long enough range
+    let mut starts: HashMap<T, usize> = HashMap::new();
+    for end in min_len..prefix_sums.len() {
+        starts
+            .entry(prefix_sums[end - min_len])
+            .or_insert(end - min_len);
+        if let Some(&start) = starts.get(&(prefix_sums[end] - target)) {
+            return Some(start..end);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [i64; 20] = [
+        35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+    ];
+
+    #[test]
+    fn test_example() {
+        assert_eq!(first_not_pair_sum(&EXAMPLE, 5), Some((14, 127)));
+        let range = contiguous_sum(&EXAMPLE, 127, 2).unwrap();
+        assert_eq!(range, 2..6);
+        assert_eq!(EXAMPLE[range], [15, 25, 47, 40]);
+    }
+
+    #[test]
+    fn test_contiguous_sum_negative_numbers() {
+        assert_eq!(contiguous_sum(&[5, -3, 4, -1, 2], 3, 1), Some(2..4));
+        assert_eq!(contiguous_sum(&[5, -3, 4, -1, 2], 20, 1), None);
+    }
+
+    #[test]
+    fn test_contiguous_sum_min_len() {
+        assert_eq!(contiguous_sum(&[3, 1, 2], 3, 1), Some(0..1));
+        assert_eq!(contiguous_sum(&[3, 1, 2], 3, 2), Some(1..3));
+        assert_eq!(contiguous_sum(&[3, 1, 2], 3, 3), None);
+        assert_eq!(contiguous_sum(&[3, 1, 2], 6, 3), Some(0..3));
+    }
+
+    #[test]
+    fn test_first_not_pair_sum_negative_numbers() {
+        assert_eq!(first_not_pair_sum(&[20, 1, -2, 3, 21], 2), Some((2, -2)));
+        assert_eq!(first_not_pair_sum(&[20, 1, 21, 22], 2), None);
+    }
+}
@@ -1,59 +1,221 @@
+use std::fmt::Debug;
+
 use aoc2022;
+use itertools::Itertools;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shape {
+    Rock,
+    Paper,
+    Scissors,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+
+impl Shape {
+    const ALL: [Shape; 3] = [Shape::Rock, Shape::Paper, Shape::Scissors];
+
+    fn from_opponent_column(c: char) -> Self {
+        match c {
+            'A' => Shape::Rock,
+            'B' => Shape::Paper,
+            'C' => Shape::Scissors,
+            _ => panic!("Invalid opponent shape: {}", c),
+        }
+    }
+
+    // The shape this one defeats
+    fn beats(self) -> Shape {
+        match self {
+            Shape::Rock => Shape::Scissors,
+            Shape::Paper => Shape::Rock,
+            Shape::Scissors => Shape::Paper,
+        }
+    }
+
+    // The shape that defeats this one
+    fn loses_to(self) -> Shape {
+        self.beats().beats()
+    }
+
+    fn score(self) -> usize {
+        match self {
+            Shape::Rock => 1,
+            Shape::Paper => 2,
+            Shape::Scissors => 3,
+        }
+    }
+
+    fn against(self, opponent: Shape) -> Outcome {
+        if self.beats() == opponent {
+            Outcome::Win
+        } else if self.loses_to() == opponent {
+            Outcome::Lose
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    // The shape to play against this one to get the desired outcome
+    fn for_outcome(self, outcome: Outcome) -> Shape {
+        match outcome {
+            Outcome::Lose => self.beats(),
+            Outcome::Draw => self,
+            Outcome::Win => self.loses_to(),
+        }
+    }
+}
+
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+
+    fn score(self) -> usize {
+        match self {
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
+        }
+    }
+}
+
+fn round_score(opponent: Shape, me: Shape) -> usize {
+    me.score() + me.against(opponent).score()
+}
+
+fn column_index(c: char) -> usize {
+    match c {
+        'X' => 0,
+        'Y' => 1,
+        'Z' => 2,
+        _ => panic!("Invalid strategy column: {}", c),
+    }
+}
+
+// A way of reading the X/Y/Z column of the strategy guide
+trait Interpretation: Debug {
+    fn my_shape(&self, opponent: Shape, column: char) -> Shape;
+
+    fn total_score(&self, guide: &[(Shape, char)]) -> usize {
+        guide
+            .iter()
+            .map(|&(opponent, column)| round_score(opponent, self.my_shape(opponent, column)))
+            .sum()
+    }
+}
+
+// X/Y/Z is the shape to play
+#[derive(Debug)]
+struct ShapeColumn([Shape; 3]);
+
+// X/Y/Z is how the round should end
+#[derive(Debug)]
+struct OutcomeColumn([Outcome; 3]);
 
-fn part1_score((p1, p2): (char, char)) -> usize {
-    match (p1, p2) {
-        ('A', 'X') => 3 + 1,
-        ('A', 'Y') => 6 + 2,
-        ('A', 'Z') => 0 + 3,
-        ('B', 'X') => 0 + 1,
-        ('B', 'Y') => 3 + 2,
-        ('B', 'Z') => 6 + 3,
-        ('C', 'X') => 6 + 1,
-        ('C', 'Y') => 0 + 2,
-        ('C', 'Z') => 3 + 3,
-        _ => panic!("unexpected!"),
-    }
-}
-
-fn part2_score((p1, p2): (char, char)) -> usize {
-    match (p1, p2) {
-        ('A', 'X') => 0 + 3,
-        ('A', 'Y') => 3 + 1,
-        ('A', 'Z') => 6 + 2,
-        ('B', 'X') => 0 + 1,
-        ('B', 'Y') => 3 + 2,
-        ('B', 'Z') => 6 + 3,
-        ('C', 'X') => 0 + 2,
-        ('C', 'Y') => 3 + 3,
-        ('C', 'Z') => 6 + 1,
-        _ => panic!("unexpected!"),
-    }
-}
-
-fn parse_line(line: String) -> (char, char) {
+impl Interpretation for ShapeColumn {
+    fn my_shape(&self, _opponent: Shape, column: char) -> Shape {
+        self.0[column_index(column)]
+    }
+}
+
+impl Interpretation for OutcomeColumn {
+    fn my_shape(&self, opponent: Shape, column: char) -> Shape {
+        opponent.for_outcome(self.0[column_index(column)])
+    }
+}
+
+// Tries every possible mapping of X/Y/Z, as either shapes or outcomes,
+// and returns the one that scores the most over the guide.
+fn best_interpretation(guide: &[(Shape, char)]) -> Box<dyn Interpretation> {
+    let shape_mappings = Shape::ALL
+        .into_iter()
+        .permutations(3)
+        .map(|p| Box::new(ShapeColumn([p[0], p[1], p[2]])) as Box<dyn Interpretation>);
+    let outcome_mappings = Outcome::ALL
+        .into_iter()
+        .permutations(3)
+        .map(|p| Box::new(OutcomeColumn([p[0], p[1], p[2]])) as Box<dyn Interpretation>);
+    shape_mappings
+        .chain(outcome_mappings)
+        .max_by_key(|interpretation| interpretation.total_score(guide))
+        .expect("There is always an interpretation")
+}
+
+fn parse_line(line: String) -> (Shape, char) {
     let mut chars = line.chars();
     let p1 = chars.next().expect("Line is missing first char");
     chars.next().expect("Line is missing second char");
     let p2 = chars.next().expect("Lines is missing third char");
-    (p1, p2)
+    (Shape::from_opponent_column(p1), p2)
+}
+
+fn generic_solution(interpretation: &dyn Interpretation) {
+    let guide = aoc2022::io::iterate_lines().map(parse_line).collect_vec();
+    println!("Total score is {}", interpretation.total_score(&guide));
+    if aoc2022::cli::has_flag("--optimize") {
+        let best = best_interpretation(&guide);
+        println!(
+            "Best interpretation is {:?} with score {}",
+            best,
+            best.total_score(&guide)
+        );
+    }
 }
 
 fn part1() {
-    let res: usize = aoc2022::io::iterate_lines()
-        .map(parse_line)
-        .map(part1_score)
-        .sum();
-    println!("Total score is {}", res);
+    generic_solution(&ShapeColumn([Shape::Rock, Shape::Paper, Shape::Scissors]));
 }
 
 fn part2() {
-    let res: usize = aoc2022::io::iterate_lines()
-        .map(parse_line)
-        .map(part2_score)
-        .sum();
-    println!("Total score is {}", res);
+    generic_solution(&OutcomeColumn([Outcome::Lose, Outcome::Draw, Outcome::Win]));
 }
 
 pub fn main() {
     aoc2022::cli::run(part1, part2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [(Shape, char); 3] = [
+        (Shape::Rock, 'Y'),
+        (Shape::Paper, 'X'),
+        (Shape::Scissors, 'Z'),
+    ];
+
+    #[test]
+    fn test_shapes() {
+        assert_eq!(Shape::Rock.beats(), Shape::Scissors);
+        assert_eq!(Shape::Rock.loses_to(), Shape::Paper);
+        for shape in Shape::ALL {
+            assert_eq!(shape.beats().loses_to(), shape);
+            assert_eq!(shape.against(shape), Outcome::Draw);
+            for outcome in Outcome::ALL {
+                assert_eq!(shape.for_outcome(outcome).against(shape), outcome);
+            }
+        }
+    }
+
+    #[test]
+    fn test_example_scores() {
+        let shapes = ShapeColumn([Shape::Rock, Shape::Paper, Shape::Scissors]);
+        assert_eq!(shapes.total_score(&EXAMPLE), 15);
+        let outcomes = OutcomeColumn([Outcome::Lose, Outcome::Draw, Outcome::Win]);
+        assert_eq!(outcomes.total_score(&EXAMPLE), 12);
+    }
+
+    #[test]
+    fn test_best_interpretation() {
+        // Winning every round with scissors, paper and rock
+        let best = best_interpretation(&EXAMPLE);
+        assert_eq!(best.total_score(&EXAMPLE), 24);
+        assert_eq!(best.my_shape(Shape::Paper, 'X'), Shape::Scissors);
+        assert_eq!(best.my_shape(Shape::Rock, 'Y'), Shape::Paper);
+        assert_eq!(best.my_shape(Shape::Scissors, 'Z'), Shape::Rock);
+    }
+}
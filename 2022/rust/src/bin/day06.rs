@@ -1,17 +1,87 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Bytes};
+use std::iter::FusedIterator;
+
 use aoc2022;
-use itertools::Itertools;
+
+// Yields the position right after every window of `k` distinct bytes,
+// reading the stream one byte at a time and stopping at the end of the line.
+// Byte counts are kept in a fixed table along with the number of byte values
+// currently repeated in the window, so each step is O(1). Once the line ends
+// it yields nothing more, even if the reader has more lines.
+struct MarkerPositions<R: BufRead> {
+    bytes: Bytes<R>,
+    k: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    repeated: usize,
+    position: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for MarkerPositions<R> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while !self.done {
+            let byte = match self.bytes.next() {
+                Some(byte) => byte.expect("could not read input"),
+                None => break,
+            };
+            if byte == b'\n' || byte == b'\r' {
+                break;
+            }
+            self.position += 1;
+            self.window.push_back(byte);
+            self.counts[byte as usize] += 1;
+            if self.counts[byte as usize] == 2 {
+                self.repeated += 1;
+            }
+            if self.window.len() > self.k {
+                let old = self.window.pop_front().unwrap();
+                self.counts[old as usize] -= 1;
+                if self.counts[old as usize] == 1 {
+                    self.repeated -= 1;
+                }
+            }
+            if self.window.len() == self.k && self.repeated == 0 {
+                return Some(self.position);
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+impl<R: BufRead> FusedIterator for MarkerPositions<R> {}
+
+// An empty window would count every position as a marker, so there are none
+fn distinct_window_positions<R: BufRead>(reader: R, k: usize) -> MarkerPositions<R> {
+    MarkerPositions {
+        bytes: reader.bytes(),
+        k,
+        window: VecDeque::with_capacity(k + 1),
+        counts: [0; 256],
+        repeated: 0,
+        position: 0,
+        done: k == 0,
+    }
+}
+
+fn distinct_window_position<R: BufRead>(reader: R, k: usize) -> Option<usize> {
+    distinct_window_positions(reader, k).next()
+}
 
 fn generic_solution(window_size: usize) {
-    let (index, _letters) = aoc2022::io::iterate_lines()
-        .next()
-        .expect("Missing input")
-        .chars()
-        .collect_vec()
-        .windows(window_size)
-        .enumerate()
-        .find(|(_i, chars)| chars.iter().unique().count() == window_size)
-        .expect("Invalid input");
-    println!("Marker is at {}", index + window_size);
+    let input = io::stdin().lock();
+    if aoc2022::cli::has_flag("--all") {
+        for position in distinct_window_positions(input, window_size) {
+            println!("Marker at {}", position);
+        }
+        return;
+    }
+    let position = distinct_window_position(input, window_size).expect("Invalid input");
+    println!("Marker is at {}", position);
 }
 
 fn part1() {
@@ -25,3 +95,47 @@ fn part2() {
 pub fn main() {
     aoc2022::cli::run(part1, part2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsmv", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    #[test]
+    fn test_samples() {
+        for (stream, packet, message) in SAMPLES {
+            assert_eq!(distinct_window_position(stream.as_bytes(), 4), Some(packet));
+            assert_eq!(
+                distinct_window_position(stream.as_bytes(), 14),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_positions() {
+        let positions = distinct_window_positions("aabcaad\n".as_bytes(), 2).collect::<Vec<_>>();
+        assert_eq!(positions, vec![3, 4, 5, 7]);
+        assert_eq!(distinct_window_position("aaaa".as_bytes(), 2), None);
+    }
+
+    #[test]
+    fn test_stops_at_end_of_line() {
+        let mut positions = distinct_window_positions("aab\nabcd\n".as_bytes(), 2);
+        assert_eq!(positions.next(), Some(3));
+        assert_eq!(positions.next(), None);
+        assert_eq!(positions.next(), None);
+    }
+
+    #[test]
+    fn test_empty_window() {
+        assert_eq!(distinct_window_position("abc".as_bytes(), 0), None);
+    }
+}
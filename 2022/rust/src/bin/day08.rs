@@ -1,112 +1,113 @@
 use aoc2022;
+use aoc2022::grid::Nearest;
 use itertools::Itertools;
 
-type Grid = Vec<Vec<isize>>;
-type VisibilityMap = Vec<Vec<(isize, isize, isize, isize)>>;
+// Characters used by the heatmap, from the lowest to the highest score
+const HEAT_SCALE: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
 
-fn pop_while<T>(stack: &mut Vec<T>, predicate: impl Fn(&T) -> bool) {
-    while let Some(val) = stack.last() {
-        if predicate(val) {
-            stack.pop();
-        } else {
-            break;
+// For every tree, the closest tree at least as tall in each direction.
+// Trees with no blocker in some direction are visible from that edge.
+struct VisibilityMap {
+    blockers: Vec<Vec<Nearest>>,
+    n_rows: usize,
+    n_cols: usize,
+}
+
+impl VisibilityMap {
+    fn new<T: Ord>(grid: &[Vec<T>]) -> Self {
+        let blockers = aoc2022::grid::nearest_at_least(grid);
+        Self {
+            n_rows: blockers.len(),
+            n_cols: blockers.first().map_or(0, |row| row.len()),
+            blockers,
         }
     }
-}
 
-fn compute_visibility_map(grid: &Grid) -> VisibilityMap {
-    // (top visibility, right visibility, bottom visibility, left visibility)
-    let d = vec![(0, 0, 0, 0); grid[0].len()];
-    let mut visibility_map = vec![d; grid.len()];
-    let mut visible_peaks: Vec<(isize, isize)>;
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        (0..self.n_rows).cartesian_product(0..self.n_cols)
+    }
 
-    for row in 0..grid.len() {
-        visible_peaks = vec![(11, -1)];
-        for col in 0..grid[row].len() {
-            let height = grid[row][col];
-            pop_while(&mut visible_peaks, |(peak_height, _peak_col)| {
-                *peak_height < height
-            });
-            let &(_peak_height, peak_col) = visible_peaks.last().expect("Something went wrong");
-            visibility_map[row][col].3 = peak_col;
-            visible_peaks.push((height, col as isize));
-        }
+    fn is_visible(&self, row: usize, col: usize) -> bool {
+        let Nearest {
+            up,
+            right,
+            down,
+            left,
+        } = self.blockers[row][col];
+        up.is_none() || right.is_none() || down.is_none() || left.is_none()
+    }
 
-        visible_peaks = vec![(11, grid[row].len() as isize)];
-        for col in (0..grid[row].len()).rev() {
-            let height = grid[row][col];
-            pop_while(&mut visible_peaks, |(peak_height, _peak_col)| {
-                *peak_height < height
-            });
-            let &(_peak_height, peak_col) = visible_peaks.last().expect("Something went wrong");
-            visibility_map[row][col].1 = peak_col;
-            visible_peaks.push((height, col as isize));
-        }
+    fn visible_count(&self) -> usize {
+        self.cells().filter(|&(r, c)| self.is_visible(r, c)).count()
     }
-    for col in 0..grid[0].len() {
-        visible_peaks = vec![(11, -1)];
-        for row in 0..grid.len() {
-            let height = grid[row][col];
-            pop_while(&mut visible_peaks, |(peak_height, _peak_col)| {
-                *peak_height < height
-            });
-            let &(_peak_height, peak_row) = visible_peaks.last().expect("Something went wrong");
-            visibility_map[row][col].0 = peak_row;
-            visible_peaks.push((height, row as isize));
-        }
 
-        visible_peaks = vec![(11, grid.len() as isize)];
-        for row in (0..grid.len()).rev() {
-            let height = grid[row][col];
-            pop_while(&mut visible_peaks, |(peak_height, _peak_col)| {
-                *peak_height < height
-            });
-            let &(_peak_height, peak_row) = visible_peaks.last().expect("Something went wrong");
-            visibility_map[row][col].2 = peak_row;
-            visible_peaks.push((height, row as isize));
-        }
+    fn scenic_score(&self, row: usize, col: usize) -> usize {
+        let blockers = self.blockers[row][col];
+        (row - blockers.up.unwrap_or(0))
+            * (blockers.right.unwrap_or(self.n_cols - 1) - col)
+            * (blockers.down.unwrap_or(self.n_rows - 1) - row)
+            * (col - blockers.left.unwrap_or(0))
+    }
+
+    fn best_scenic_score(&self) -> usize {
+        self.cells()
+            .map(|(r, c)| self.scenic_score(r, c))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn render_visible(&self) -> String {
+        let canvas = (0..self.n_rows)
+            .map(|r| {
+                (0..self.n_cols)
+                    .map(|c| if self.is_visible(r, c) { '#' } else { '.' })
+                    .collect_vec()
+            })
+            .collect_vec();
+        aoc2022::io::print_matrix(&canvas)
     }
-    visibility_map
-}
 
-fn is_visible(visibility_map: &VisibilityMap, row: usize, col: usize) -> bool {
-    let (n_rows, n_cols) = (visibility_map.len(), visibility_map[0].len());
-    let (vt, vr, vb, vl) = visibility_map[row][col];
-    vt == -1 || vb == n_rows as isize || vl == -1 || vr == n_cols as isize
+    fn render_heatmap(&self) -> String {
+        let max_score = self.best_scenic_score().max(1);
+        let canvas = (0..self.n_rows)
+            .map(|r| {
+                (0..self.n_cols)
+                    .map(|c| {
+                        let level = self.scenic_score(r, c) * (HEAT_SCALE.len() - 1) / max_score;
+                        HEAT_SCALE[level]
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        aoc2022::io::print_matrix(&canvas)
+    }
 }
 
-fn scenic_score(visibility_map: &VisibilityMap, row: usize, col: usize) -> usize {
-    let (n_rows, n_cols) = (visibility_map.len(), visibility_map[0].len());
-    let (tp, rp, bp, lp) = visibility_map[row][col];
-    (row - tp.max(0) as usize)
-        * ((rp as usize).min(n_cols - 1) - col)
-        * ((bp as usize).min(n_rows - 1) - row)
-        * (col - lp.max(0) as usize)
+fn read_visibility_map() -> VisibilityMap {
+    let grid = aoc2022::io::read_matrix(|c| c.to_digit(10).expect("Invalid digit"));
+    VisibilityMap::new(&grid)
 }
 
 fn part1() {
-    let grid = aoc2022::io::read_matrix(|c| c.to_digit(10).expect("Invalid digit") as isize);
-    let visibility_map = compute_visibility_map(&grid);
-    let mut count = 0;
-    for row in 0..grid.len() {
-        for col in 0..grid[row].len() {
-            if is_visible(&visibility_map, row, col) {
-                count += 1;
-            }
-        }
+    let visibility_map = read_visibility_map();
+    if aoc2022::cli::has_flag("--visible") {
+        println!("{}\n", visibility_map.render_visible());
     }
-    println!("There are {} trees visible from outside the grid", count);
+    println!(
+        "There are {} trees visible from outside the grid",
+        visibility_map.visible_count()
+    );
 }
 
 fn part2() {
-    let grid = aoc2022::io::read_matrix(|c| c.to_digit(10).expect("Invalid digit") as isize);
-    let visibility_map = compute_visibility_map(&grid);
-    let max_score = (0..grid.len())
-        .cartesian_product(0..grid[0].len())
-        .map(|(r, c)| scenic_score(&visibility_map, r, c))
-        .max()
-        .unwrap();
-    println!("The best scenic score is {}", max_score);
+    let visibility_map = read_visibility_map();
+    if aoc2022::cli::has_flag("--heatmap") {
+        println!("{}\n", visibility_map.render_heatmap());
+    }
+    println!(
+        "The best scenic score is {}",
+        visibility_map.best_scenic_score()
+    );
 }
 
 pub fn main() {
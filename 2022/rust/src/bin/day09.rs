@@ -1,4 +1,5 @@
 use aoc2022;
+use aoc_common::vec2::Vec2;
 use itertools::Itertools;
 use std::collections::HashSet;
 
@@ -10,7 +11,7 @@ enum Direction {
     Left,
 }
 type Motion = (Direction, u8);
-type Pos = (isize, isize);
+type Pos = Vec2;
 
 fn parse_line(line: &str) -> Motion {
     let (direction, steps_str) = line.split(" ").collect_tuple().expect("Invalid line");
@@ -28,30 +29,28 @@ fn expand_motion(motion: Motion) -> impl Iterator<Item = Motion> {
     (1..=motion.1).map(move |_| (motion.0, 1))
 }
 
-fn apply_motion((x, y): Pos, motion: Motion) -> Pos {
-    match motion {
-        (Direction::Up, steps) => (x, y + steps as isize),
-        (Direction::Down, steps) => (x, y - steps as isize),
-        (Direction::Right, steps) => (x + steps as isize, y),
-        (Direction::Left, steps) => (x - steps as isize, y),
-    }
+fn apply_motion(pos: Pos, (direction, steps): Motion) -> Pos {
+    let unit = match direction {
+        Direction::Up => Vec2::NORTH,
+        Direction::Down => Vec2::SOUTH,
+        Direction::Right => Vec2::EAST,
+        Direction::Left => Vec2::WEST,
+    };
+    pos + unit * steps as isize
 }
 
-fn follow((prev_x, prev_y): Pos, (knot_x, knot_y): Pos) -> Pos {
-    match (prev_x - knot_x, prev_y - knot_y) {
-        (-1..=1, -1..=1) => (knot_x, knot_y),
-        diffs @ (-2..=2, -2..=2) => (knot_x + diffs.0.signum(), knot_y + diffs.1.signum()),
-        diffs => panic!(
-            "Knot is too far from previous one! {:?} [{:?}]",
-            diffs,
-            (prev_x, prev_y)
-        ),
+fn follow(prev: Pos, knot: Pos) -> Pos {
+    let diff = prev - knot;
+    match diff.chebyshev() {
+        0..=1 => knot,
+        2 => knot + diff.signum(),
+        _ => panic!("Knot is too far from previous one! {} [{}]", diff, prev),
     }
 }
 
 fn generic_solution(n_knots: usize) {
-    let mut knot_positions: Vec<(isize, isize)> = vec![(0, 0); n_knots];
-    let mut unique_tail_positions: HashSet<Pos> = HashSet::from_iter(vec![(0, 0)]);
+    let mut knot_positions: Vec<Pos> = vec![Vec2::ZERO; n_knots];
+    let mut unique_tail_positions: HashSet<Pos> = HashSet::from_iter(vec![Vec2::ZERO]);
     for motion in aoc2022::io::iterate_lines()
         .map(|l| parse_line(l.as_str()))
         .flat_map(|m| expand_motion(m))
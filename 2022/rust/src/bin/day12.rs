@@ -1,85 +1,163 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 
 use aoc2022;
 use itertools::Itertools;
 
-fn height(c: char) -> usize {
-    match c as u8 {
-        83 => 1,
-        69 => 26,
-        n @ 97..=122 => (n - 96) as usize,
+type Coord = (usize, usize);
+
+fn height(c: char) -> u8 {
+    match c {
+        'S' => 0,
+        'E' => 25,
+        'a'..='z' => c as u8 - b'a',
         _ => panic!("Invalid char in input"),
     }
 }
 
-fn adj(
-    map: &Vec<Vec<char>>,
-    row: usize,
-    col: usize,
-    inverted: bool,
-) -> impl Iterator<Item = (usize, usize)> + '_ {
-    let row_range = (row as isize - 1).max(0) as usize..(row + 2).min(map.len());
-    let col_range = (col as isize - 1).max(0) as usize..(col + 2).min(map[0].len());
-    row_range
-        .cartesian_product(col_range)
-        .filter(move |&(r, c)| {
-            let h = if inverted {
-                height(map[r][c]) >= height(map[row][col]) - 1
-            } else {
-                height(map[r][c]) <= height(map[row][col]) + 1
-            };
-            h && (r == row) ^ (c == col)
-        })
+struct Heightmap {
+    heights: Vec<Vec<u8>>,
+    start: Coord,
+    end: Coord,
+}
+
+// Distance of every cell from the closest source, plus the cell it was
+// reached from so the shortest paths can be rebuilt.
+struct DistanceField {
+    distances: Vec<Vec<Option<usize>>>,
+    parents: Vec<Vec<Option<Coord>>>,
 }
 
-fn shortest_path_length(
-    map: &Vec<Vec<char>>,
-    start: (usize, usize),
-    end_fn: impl Fn(char) -> bool,
-    inverted: bool,
-) -> usize {
-    let mut next = VecDeque::new();
-    next.push_back((start, 0));
-    let mut visited: HashSet<(usize, usize)> = HashSet::new();
-    while let Some(((r, c), steps)) = next.pop_front() {
-        if visited.contains(&(r, c)) {
-            continue;
+impl DistanceField {
+    fn distance(&self, (r, c): Coord) -> Option<usize> {
+        self.distances[r][c]
+    }
+
+    // Path from the closest source to the target, both included
+    fn path_to(&self, target: Coord) -> Option<Vec<Coord>> {
+        self.distance(target)?;
+        let mut path = vec![target];
+        while let Some((r, c)) = path.last().copied() {
+            match self.parents[r][c] {
+                Some(parent) => path.push(parent),
+                None => break,
+            }
         }
-        visited.insert((r, c));
-        if end_fn(map[r][c]) {
-            return steps;
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl Heightmap {
+    fn read() -> Self {
+        let chars = aoc2022::io::read_matrix(|c| c);
+        let find = |target: char| {
+            chars
+                .iter()
+                .enumerate()
+                .find_map(|(r, row)| row.iter().position(|&c| c == target).map(|c| (r, c)))
+                .unwrap_or_else(|| panic!("Missing {} in input", target))
+        };
+        Self {
+            start: find('S'),
+            end: find('E'),
+            heights: chars
+                .iter()
+                .map(|row| row.iter().map(|&c| height(c)).collect())
+                .collect(),
         }
-        for (next_r, next_c) in adj(&map, r, c, inverted) {
-            next.push_back(((next_r, next_c), steps + 1));
+    }
+
+    fn n_rows(&self) -> usize {
+        self.heights.len()
+    }
+
+    fn n_cols(&self) -> usize {
+        self.heights[0].len()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = Coord> {
+        (0..self.n_rows()).cartesian_product(0..self.n_cols())
+    }
+
+    fn height(&self, (r, c): Coord) -> u8 {
+        self.heights[r][c]
+    }
+
+    fn climbable_neighbours(&self, from: Coord) -> impl Iterator<Item = Coord> + '_ {
+        aoc2022::grid::taxicab_neighbours(self.n_cols(), self.n_rows(), from)
+            .filter(move |&to| self.height(to) <= self.height(from) + 1)
+    }
+
+    // Breadth-first search started from all the sources at once
+    fn distances_from(&self, sources: impl IntoIterator<Item = Coord>) -> DistanceField {
+        let mut field = DistanceField {
+            distances: vec![vec![None; self.n_cols()]; self.n_rows()],
+            parents: vec![vec![None; self.n_cols()]; self.n_rows()],
+        };
+        let mut queue = VecDeque::new();
+        for (r, c) in sources {
+            field.distances[r][c] = Some(0);
+            queue.push_back((r, c));
+        }
+        while let Some(pos) = queue.pop_front() {
+            let steps = field.distance(pos).unwrap();
+            for (r, c) in self.climbable_neighbours(pos) {
+                if field.distances[r][c].is_none() {
+                    field.distances[r][c] = Some(steps + 1);
+                    field.parents[r][c] = Some(pos);
+                    queue.push_back((r, c));
+                }
+            }
+        }
+        field
+    }
+
+    // Draws the path the way the puzzle text does: every step is an arrow
+    // pointing to the next one, cells off the path are dots.
+    fn render_path(&self, path: &[Coord]) -> String {
+        let mut canvas = vec![vec!['.'; self.n_cols()]; self.n_rows()];
+        for (&(r, c), &(next_r, next_c)) in path.iter().tuple_windows() {
+            canvas[r][c] = match (next_r as isize - r as isize, next_c as isize - c as isize) {
+                (-1, 0) => '^',
+                (1, 0) => 'v',
+                (0, -1) => '<',
+                (0, 1) => '>',
+                _ => panic!("Path steps are not adjacent"),
+            };
+        }
+        if let Some(&(r, c)) = path.last() {
+            canvas[r][c] = 'E';
+        }
+        aoc2022::io::print_matrix(&canvas)
+    }
+}
+
+fn report(map: &Heightmap, field: &DistanceField) {
+    match field.path_to(map.end) {
+        Some(path) => {
+            if aoc2022::cli::has_flag("--path") {
+                println!("{}\n", map.render_path(&path));
+            }
+            println!("{}", path.len() - 1);
         }
+        None => println!("The top can't be reached"),
     }
-    0
 }
 
 fn part1() {
-    let map: Vec<Vec<char>> = aoc2022::io::iterate_lines()
-        .map(|line| line.chars().collect::<Vec<char>>())
-        .collect();
-    let start = map
-        .iter()
-        .enumerate()
-        .find_map(|(r, row)| row.iter().position(|&c| c == 'S').map(|c| (r, c)))
-        .unwrap();
-    let min_steps = shortest_path_length(&map, start, |c| c == 'E', false);
-    println!("{}", min_steps);
+    let map = Heightmap::read();
+    let field = map.distances_from([map.start]);
+    report(&map, &field);
 }
 
 fn part2() {
-    let map: Vec<Vec<char>> = aoc2022::io::iterate_lines()
-        .map(|line| line.chars().collect::<Vec<char>>())
-        .collect();
-    let start = map
-        .iter()
-        .enumerate()
-        .find_map(|(r, row)| row.iter().position(|&c| c == 'E').map(|c| (r, c)))
-        .unwrap();
-    let min_steps = shortest_path_length(&map, start, |c| c == 'a' || c == 'S', true);
-    println!("{}", min_steps);
+    let map = Heightmap::read();
+    let lowest_cells = map
+        .cells()
+        .filter(|&pos| map.height(pos) == 0)
+        .collect_vec();
+    let field = map.distances_from(lowest_cells);
+    report(&map, &field);
 }
 
 pub fn main() {
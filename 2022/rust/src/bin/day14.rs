@@ -39,7 +39,7 @@ fn parse_path(line: &str) -> Vec<Line> {
         .collect_vec()
 }
 
-fn bounds(paths: &Vec<Vec<Line>>) -> ((usize, usize), (usize, usize)) {
+fn bounds(paths: &[Vec<Line>]) -> ((usize, usize), (usize, usize)) {
     let (xs, ys): (Vec<usize>, Vec<usize>) = paths
         .iter()
         .flat_map(|path| path.iter())
@@ -63,72 +63,127 @@ fn bounds(paths: &Vec<Vec<Line>>) -> ((usize, usize), (usize, usize)) {
     (x_bounds, y_bounds)
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Floor {
+    // Sand falling below the lowest rock is lost forever
+    Abyss,
+    // An infinite rock floor two units below the lowest rock
+    Solid,
+}
+
 struct Map {
     grid: Vec<Vec<Tile>>,
     sand_source: Coord,
+    // Positions of the grain currently falling; the last one is where the
+    // next grain resumes from, since every grain follows its predecessor's
+    // path up to the point where the predecessor came to rest.
+    path: Vec<Coord>,
     pub left: usize,
-    pub right: usize,
+    pub top: usize,
     pub width: usize,
     pub height: usize,
 }
 
 impl Map {
-    fn from_rock_paths(rock_paths: &Vec<Vec<Line>>, add_floor: bool) -> Self {
-        let ((min_x, max_x), (_, max_y)) = bounds(rock_paths);
-        let min_y = 0;
-        let width = (max_x - min_x + 1) as usize;
-        let height = (max_y - min_y + 1) as usize;
-        let mut map = vec![vec![Tile::Air; 1001]; 1001];
+    fn from_rock_paths(
+        rock_paths: &[Vec<Line>],
+        sand_source: Coord,
+        floor: Floor,
+    ) -> Result<Self, String> {
+        let ((min_x, max_x), (min_y, max_y)) = bounds(rock_paths);
+        let (source_x, source_y) = sand_source;
+        let top = min_y.min(source_y);
+        let (left, right, bottom) = match floor {
+            // A source below the lowest rock pours straight into the abyss
+            Floor::Abyss => (
+                min_x.min(source_x),
+                max_x.max(source_x),
+                max_y.max(source_y),
+            ),
+            Floor::Solid => {
+                // Sand can't spread further than the triangle below the
+                // source, whose base lies on the row just above the floor.
+                let floor_y = max_y + 2;
+                if source_y >= floor_y {
+                    return Err(format!(
+                        "sand source {:?} is not above the floor at y={}",
+                        sand_source, floor_y
+                    ));
+                }
+                let spread = floor_y - source_y;
+                (
+                    min_x.min(source_x.saturating_sub(spread)),
+                    max_x.max(source_x + spread),
+                    floor_y,
+                )
+            }
+        };
+        let width = right - left + 1;
+        let height = bottom - top + 1;
+        let mut map = Self {
+            grid: vec![vec![Tile::Air; width]; height],
+            sand_source,
+            path: vec![],
+            left,
+            top,
+            width,
+            height,
+        };
         for path in rock_paths {
             for (from, to) in path {
                 for x in std::cmp::min(from.0, to.0)..=std::cmp::max(from.0, to.0) {
                     for y in std::cmp::min(from.1, to.1)..=std::cmp::max(from.1, to.1) {
-                        map[y][x] = Tile::Rock;
+                        map.set((x, y), Tile::Rock);
                     }
                 }
             }
         }
-        if add_floor {
-            for x in 0..1001 {
-                map[height + 1][x] = Tile::Rock;
+        if floor == Floor::Solid {
+            for x in left..=right {
+                map.set((x, bottom), Tile::Rock);
             }
         }
-        Self {
-            grid: map,
-            sand_source: (500, 0),
-            left: if add_floor { 0 } else { min_x },
-            right: if add_floor { 1000 } else { max_x },
-            width: if add_floor { 1000 } else { width },
-            height: if add_floor { height + 2 } else { height },
-        }
+        Ok(map)
     }
 
     fn in_bounds(&self, (x, y): Coord) -> bool {
-        x >= self.left && x < self.right && y < self.height
+        x >= self.left && x < self.left + self.width && y >= self.top && y < self.top + self.height
+    }
+
+    fn get(&self, (x, y): Coord) -> Tile {
+        self.grid[y - self.top][x - self.left]
+    }
+
+    fn set(&mut self, (x, y): Coord, tile: Tile) {
+        self.grid[y - self.top][x - self.left] = tile;
     }
 
+    // Returns where the next grain of sand comes to rest, or None if it
+    // falls into the abyss or the source is already blocked.
     fn drop_sand(&mut self) -> Option<Coord> {
-        let mut sand_pos = self.sand_source;
+        if self.path.is_empty() {
+            if self.get(self.sand_source) != Tile::Air {
+                return None;
+            }
+            self.path.push(self.sand_source);
+        }
         loop {
-            let (sand_x, sand_y) = sand_pos;
-            let new_sand_pos = vec![
-                (sand_x, sand_y + 1),
-                (sand_x - 1, sand_y + 1),
-                (sand_x + 1, sand_y + 1),
-            ]
-            .into_iter()
-            .find(|&(x, y)| !self.in_bounds((x, y)) || self.grid[y][x] == Tile::Air);
-            match new_sand_pos {
+            let (sand_x, sand_y) = *self.path.last()?;
+            let next_pos = [Some(sand_x), sand_x.checked_sub(1), Some(sand_x + 1)]
+                .into_iter()
+                .map(|x| x.map(|x| (x, sand_y + 1)))
+                .find(|pos| match pos {
+                    Some(pos) => !self.in_bounds(*pos) || self.get(*pos) == Tile::Air,
+                    None => true,
+                });
+            match next_pos {
                 None => {
-                    self.grid[sand_y][sand_x] = Tile::Sand;
+                    self.set((sand_x, sand_y), Tile::Sand);
+                    self.path.pop();
                     return Some((sand_x, sand_y));
                 }
-                Some((x, y)) => {
-                    if !self.in_bounds((x, y)) {
-                        return None;
-                    }
-                    sand_pos = (x, y);
-                }
+                Some(Some(pos)) if self.in_bounds(pos) => self.path.push(pos),
+                Some(_) => return None,
             }
         }
     }
@@ -136,61 +191,87 @@ impl Map {
 
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for y in 0..self.height {
-            for x in self.left..self.left + self.width {
-                write!(f, "{}", self.grid[y][x]).expect("Eh");
+        for row in &self.grid {
+            for tile in row {
+                write!(f, "{}", tile)?;
             }
-            write!(f, "\n");
+            writeln!(f)?;
         }
-        write!(f, "\n")
+        Ok(())
     }
 }
 
-fn part1() {
+// The sand source can be moved with `--source x,y`
+fn read_map(floor: Floor) -> Map {
     let paths = aoc2022::io::iterate_lines()
         .map(|line| parse_path(&line))
         .collect_vec();
-    let mut map = Map::from_rock_paths(&paths, false);
-    println!("{}", map);
-    println!();
+    let source = aoc2022::cli::flag_value("--source").map_or((500, 0), |s| parse_coord(&s));
+    Map::from_rock_paths(&paths, source, floor).unwrap_or_else(|e| panic!("Invalid map: {}", e))
+}
+
+// Drops sand until it stops settling and prints the final map. With
+// `--trace`, also prints the map after every grain.
+fn pour_sand(map: &mut Map) -> usize {
+    let trace = aoc2022::cli::has_flag("--trace");
     let mut n = 0;
-    loop {
-        match map.drop_sand() {
-            Some((x, y)) => {
-                println!("Sand dropped to {:?}", (x, y));
-                println!("{}", map);
-                println!();
-                n += 1;
-            }
-            None => {
-                println!("Sand before abyss {}", n);
-                return;
-            }
+    while let Some(pos) = map.drop_sand() {
+        if trace {
+            println!("Sand dropped to {:?}", pos);
+            println!("{}", map);
         }
+        n += 1;
     }
+    println!("{}", map);
+    n
+}
+
+fn part1() {
+    let mut map = read_map(Floor::Abyss);
+    let n = pour_sand(&mut map);
+    println!("Sand before abyss {}", n);
 }
 
 fn part2() {
-    let paths = aoc2022::io::iterate_lines()
-        .map(|line| parse_path(&line))
-        .collect_vec();
-    let mut map = Map::from_rock_paths(&paths, true);
-    let mut n = 0;
-    loop {
-        match map.drop_sand() {
-            Some((500, 0)) => {
-                println!("Sand dropped until stuck {}", n + 1);
-                return;
-            }
-            Some((x, y)) => {
-                println!("Sand dropped to {:?}", (x, y));
-                n += 1;
-            }
-            _ => panic!("Unexpected"),
-        }
-    }
+    let mut map = read_map(Floor::Solid);
+    let n = pour_sand(&mut map);
+    println!("Sand dropped until stuck {}", n);
 }
 
 pub fn main() {
     aoc2022::cli::run(part1, part2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9";
+
+    fn map(source: Coord, floor: Floor) -> Result<Map, String> {
+        let paths = EXAMPLE.lines().map(parse_path).collect_vec();
+        Map::from_rock_paths(&paths, source, floor)
+    }
+
+    fn count_grains(source: Coord, floor: Floor) -> usize {
+        let mut map = map(source, floor).unwrap();
+        std::iter::from_fn(|| map.drop_sand()).count()
+    }
+
+    #[test]
+    fn test_drop_sand() {
+        assert_eq!(count_grains((500, 0), Floor::Abyss), 24);
+        assert_eq!(count_grains((500, 0), Floor::Solid), 93);
+    }
+
+    #[test]
+    fn test_other_sources() {
+        // Far from the rocks, the sand fills the whole triangle above the floor
+        assert_eq!(count_grains((480, 0), Floor::Solid), 11 * 11);
+        assert_eq!(count_grains((480, 0), Floor::Abyss), 0);
+        assert_eq!(count_grains((500, 20), Floor::Abyss), 0);
+        assert_eq!(count_grains((500, 10), Floor::Solid), 1);
+        assert!(map((500, 11), Floor::Solid).is_err());
+        assert!(map((500, 20), Floor::Solid).is_err());
+    }
+}
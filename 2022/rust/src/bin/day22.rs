@@ -1,10 +1,11 @@
 use itertools::Itertools;
 
 use aoc2022;
+use aoc_common::vec2::Vec2;
 
 type Board = Vec<Vec<char>>;
 type BoardBoundaries = (Vec<(usize, usize)>, Vec<(usize, usize)>);
-type Pos = (usize, usize, Direction);
+type Pos = (Vec2, Direction);
 
 #[derive(Clone, Copy, Debug)]
 enum Direction {
@@ -14,6 +15,18 @@ enum Direction {
     Left,
 }
 
+impl Direction {
+    // Rows grow downwards, so going up decreases y
+    fn unit(self) -> Vec2 {
+        match self {
+            Up => Vec2::NORTH,
+            Right => Vec2::EAST,
+            Down => Vec2::SOUTH,
+            Left => Vec2::WEST,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum TurnDirection {
     Clockwise,
@@ -50,47 +63,30 @@ fn read_input() -> (Board, Vec<Motion>) {
     (board, motion_plan)
 }
 
+/// Brings a coordinate that just stepped out of `min..=max` back in from the
+/// opposite side
+fn wrap(coord: isize, (min, max): (usize, usize)) -> isize {
+    if coord < min as isize {
+        max as isize
+    } else if coord > max as isize {
+        min as isize
+    } else {
+        coord
+    }
+}
+
 /// Advances by one step in the current facing direction,
 /// handling walls and wraparounds
-fn advance(board: &Board, board_boundaries: &BoardBoundaries, position: Pos) -> Pos {
-    let new_position @ (x, y, _d) = match position {
-        (x, y, Up) => {
-            let (min_y, max_y) = board_boundaries.1[x];
-            if y == 0 || y - 1 < min_y {
-                (x, max_y, Up)
-            } else {
-                (x, y - 1, Up)
-            }
-        }
-        (x, y, Down) => {
-            let (min_y, max_y) = board_boundaries.1[x];
-            if y + 1 > max_y {
-                (x, min_y, Down)
-            } else {
-                (x, y + 1, Down)
-            }
-        }
-        (x, y, Right) => {
-            let (min_x, max_x) = board_boundaries.0[y];
-            if x + 1 > max_x {
-                (min_x, y, Right)
-            } else {
-                (x + 1, y, Right)
-            }
-        }
-        (x, y, Left) => {
-            let (min_x, max_x) = board_boundaries.0[y];
-            if x == 0 || x - 1 < min_x {
-                (max_x, y, Left)
-            } else {
-                (x - 1, y, Left)
-            }
-        }
-    };
-    match board[y][x] {
-        '#' => position,
-        '.' => new_position,
-        _ => panic!("Unexpected move to invalid board cell {},{}", x, y),
+fn advance(board: &Board, board_boundaries: &BoardBoundaries, (at, facing): Pos) -> Pos {
+    let mut next = at + facing.unit();
+    match facing {
+        Up | Down => next.y = wrap(next.y, board_boundaries.1[at.x as usize]),
+        Right | Left => next.x = wrap(next.x, board_boundaries.0[at.y as usize]),
+    }
+    match board[next.y as usize][next.x as usize] {
+        '#' => (at, facing),
+        '.' => (next, facing),
+        _ => panic!("Unexpected move to invalid board cell {}", next),
     }
 }
 
@@ -101,14 +97,14 @@ fn apply_motion(
     motion: Motion,
 ) -> Pos {
     match (motion, position) {
-        (Turn(Clockwise), (x, y, Up)) => (x, y, Right),
-        (Turn(Clockwise), (x, y, Right)) => (x, y, Down),
-        (Turn(Clockwise), (x, y, Down)) => (x, y, Left),
-        (Turn(Clockwise), (x, y, Left)) => (x, y, Up),
-        (Turn(CounterClockwise), (x, y, Up)) => (x, y, Left),
-        (Turn(CounterClockwise), (x, y, Right)) => (x, y, Up),
-        (Turn(CounterClockwise), (x, y, Down)) => (x, y, Right),
-        (Turn(CounterClockwise), (x, y, Left)) => (x, y, Down),
+        (Turn(Clockwise), (at, Up)) => (at, Right),
+        (Turn(Clockwise), (at, Right)) => (at, Down),
+        (Turn(Clockwise), (at, Down)) => (at, Left),
+        (Turn(Clockwise), (at, Left)) => (at, Up),
+        (Turn(CounterClockwise), (at, Up)) => (at, Left),
+        (Turn(CounterClockwise), (at, Right)) => (at, Up),
+        (Turn(CounterClockwise), (at, Down)) => (at, Right),
+        (Turn(CounterClockwise), (at, Left)) => (at, Down),
         (Forward(steps), pos) => {
             (1..=steps).fold(pos, |pos, _| advance(board, board_boundaries, pos))
         }
@@ -169,20 +165,20 @@ fn precompute_board_boundaries(board: &Board) -> BoardBoundaries {
     (row_boundaries, col_boundaries)
 }
 
-fn password((x, y, direction): Pos) -> usize {
+fn password((at, direction): Pos) -> usize {
     let direction_number = match direction {
         Right => 0,
         Down => 1,
         Left => 2,
         Up => 3,
     };
-    1000 * (y + 1) + 4 * (x + 1) + direction_number
+    1000 * (at.y as usize + 1) + 4 * (at.x as usize + 1) + direction_number
 }
 
 fn part1() {
     let (board, motion_plan) = read_input();
     let boundaries = precompute_board_boundaries(&board);
-    let mut pos: Pos = (boundaries.0[0].0, 0, Right);
+    let mut pos: Pos = (Vec2::new(boundaries.0[0].0 as isize, 0), Right);
     // println!("Starting position: {:?}", pos);
     for motion in motion_plan {
         // println!("{:?}", motion);
@@ -192,9 +188,9 @@ fn part1() {
     println!(
         "The final password is {} (final pos: row {}, col {}, facing {:?})",
         password(pos),
-        pos.1 + 1,
-        pos.0 + 1,
-        pos.2
+        pos.0.y + 1,
+        pos.0.x + 1,
+        pos.1
     );
 }
 
@@ -1,48 +1,237 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
 use aoc2022;
 
-fn to_decimal(snafu: &str) -> isize {
-    snafu.chars().rev().enumerate().fold(0, |n, (i, c)| {
-        let mul = 5_isize.pow(i as u32);
-        match c {
-            '2' => n + mul * 2,
-            '1' => n + mul,
-            '0' => n,
-            '-' => n - mul,
-            '=' => n - mul * 2,
-            _ => panic!("Invalid SNAFU digit"),
-        }
-    })
-}
-
-fn to_snafu(n: isize) -> String {
-    let mut m = n;
-    let mut snafu = String::new();
-    while m > 0 {
-        let (snafu_digit, remainder) = match m % 5 {
-            0 => ('0', 0),
-            1 => ('1', 0),
-            2 => ('2', 0),
-            3 => ('=', 2),
-            4 => ('-', 1),
-            _ => panic!("The modulo operator doesn't work.."),
-        };
-        snafu.push(snafu_digit);
-        m = (m + remainder) / 5;
-    }
-    snafu.chars().rev().collect()
+// Balanced base-5 number with digits in -2..=2, stored least significant
+// first and without leading zeros, so zero has no digits at all.
+// Operations work digit by digit and never go through a machine integer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Snafu {
+    digits: Vec<i8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseSnafuError {
+    Empty,
+    InvalidDigit(char),
+}
+
+impl Snafu {
+    // Turns arbitrary per-position coefficients into balanced digits. The
+    // carry works in i128 so coefficients up to the i64 bounds don't overflow.
+    fn normalize(coefficients: impl IntoIterator<Item = i64>) -> Self {
+        let mut digits = vec![];
+        let mut carry = 0_i128;
+        let mut coefficients = coefficients.into_iter();
+        loop {
+            let value = match coefficients.next() {
+                Some(coefficient) => coefficient as i128 + carry,
+                None if carry != 0 => carry,
+                None => break,
+            };
+            let digit = (value + 2).rem_euclid(5) - 2;
+            carry = (value - digit) / 5;
+            digits.push(digit as i8);
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self { digits }
+    }
+
+    // Partial sums can go past the i64 bounds even when the result doesn't,
+    // e.g. for i64::MIN, so they're kept in i128
+    fn to_decimal(&self) -> Option<i64> {
+        let n = self
+            .digits
+            .iter()
+            .rev()
+            .try_fold(0_i128, |n, &d| n.checked_mul(5)?.checked_add(d as i128))?;
+        i64::try_from(n).ok()
+    }
+}
+
+impl From<i64> for Snafu {
+    fn from(n: i64) -> Self {
+        Self::normalize([n])
+    }
+}
+
+impl FromStr for Snafu {
+    type Err = ParseSnafuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseSnafuError::Empty);
+        }
+        let digits = s
+            .chars()
+            .rev()
+            .map(|c| match c {
+                '2' => Ok(2),
+                '1' => Ok(1),
+                '0' => Ok(0),
+                '-' => Ok(-1),
+                '=' => Ok(-2),
+                _ => Err(ParseSnafuError::InvalidDigit(c)),
+            })
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(Self::normalize(digits))
+    }
+}
+
+impl fmt::Display for Snafu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        for digit in self.digits.iter().rev() {
+            let c = match digit {
+                2 => '2',
+                1 => '1',
+                0 => '0',
+                -1 => '-',
+                -2 => '=',
+                _ => unreachable!("SNAFU digits are normalized"),
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl Add for &Snafu {
+    type Output = Snafu;
+
+    fn add(self, other: &Snafu) -> Snafu {
+        let len = self.digits.len().max(other.digits.len());
+        let digit = |digits: &[i8], i| *digits.get(i).unwrap_or(&0) as i64;
+        Snafu::normalize((0..len).map(|i| digit(&self.digits, i) + digit(&other.digits, i)))
+    }
+}
+
+impl Add for Snafu {
+    type Output = Snafu;
+
+    fn add(self, other: Snafu) -> Snafu {
+        &self + &other
+    }
+}
+
+impl Neg for Snafu {
+    type Output = Snafu;
+
+    fn neg(self) -> Snafu {
+        Snafu {
+            digits: self.digits.into_iter().map(|d| -d).collect(),
+        }
+    }
+}
+
+impl Sub for Snafu {
+    type Output = Snafu;
+
+    fn sub(self, other: Snafu) -> Snafu {
+        self + -other
+    }
+}
+
+impl Mul for &Snafu {
+    type Output = Snafu;
+
+    fn mul(self, other: &Snafu) -> Snafu {
+        if self.digits.is_empty() || other.digits.is_empty() {
+            return Snafu::default();
+        }
+        let mut coefficients = vec![0_i64; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            for (j, &b) in other.digits.iter().enumerate() {
+                coefficients[i + j] += a as i64 * b as i64;
+            }
+        }
+        Snafu::normalize(coefficients)
+    }
+}
+
+impl Mul for Snafu {
+    type Output = Snafu;
+
+    fn mul(self, other: Snafu) -> Snafu {
+        &self * &other
+    }
+}
+
+impl Sum for Snafu {
+    fn sum<I: Iterator<Item = Snafu>>(iter: I) -> Snafu {
+        iter.fold(Snafu::default(), |acc, n| acc + n)
+    }
 }
 
 fn part1() {
-    let res: isize = aoc2022::io::iterate_lines()
-        .map(|line| to_decimal(line.trim().as_ref()))
+    let res: Snafu = aoc2022::io::iterate_lines()
+        .map(|line| line.trim().parse::<Snafu>().expect("Invalid SNAFU number"))
         .sum();
-    println!("Sum is {}, in SNAFU: {}", res, to_snafu(res));
+    match res.to_decimal() {
+        Some(n) => println!("Sum is {}, in SNAFU: {}", n, res),
+        None => println!("Sum in SNAFU is {}", res),
+    }
 }
 
 fn part2() {
-    todo!();
+    println!("Day 25 has no second part, just push the big red button!");
 }
 
 pub fn main() {
     aoc2022::cli::run(part1, part2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snafu(s: &str) -> Snafu {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_decimal_round_trip() {
+        for n in -200..=200 {
+            let s = Snafu::from(n);
+            assert_eq!(s.to_decimal(), Some(n));
+            assert_eq!(snafu(&s.to_string()), s);
+        }
+        assert_eq!(Snafu::from(0).to_string(), "0");
+        assert_eq!(Snafu::from(2022).to_string(), "1=11-2");
+        assert_eq!(Snafu::from(-3).to_string(), "-2");
+        for n in [i64::MAX, i64::MIN, i64::MAX - 2, i64::MIN + 2] {
+            let s = Snafu::from(n);
+            assert_eq!(s.to_decimal(), Some(n));
+            assert_eq!(snafu(&s.to_string()), s);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(snafu("1=-0-2") + snafu("12111"), Snafu::from(1747 + 906));
+        assert_eq!(
+            snafu("2=") - snafu("1121-1110-1=0"),
+            Snafu::from(8 - 314159265)
+        );
+        assert_eq!(snafu("1=11-2") * snafu("-"), Snafu::from(-2022));
+        assert_eq!(
+            "1=3".parse::<Snafu>(),
+            Err(ParseSnafuError::InvalidDigit('3'))
+        );
+    }
+
+    #[test]
+    fn test_no_overflow() {
+        let huge = snafu(&"2".repeat(100));
+        let sum: Snafu = vec![huge.clone(), huge.clone(), -huge].into_iter().sum();
+        assert_eq!(sum, snafu(&"2".repeat(100)));
+        assert_eq!(sum.to_decimal(), None);
+    }
+}
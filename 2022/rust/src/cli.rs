@@ -5,3 +5,15 @@ pub fn run(part1_fn: impl Fn() -> (), part2_fn: impl Fn() -> ()) {
         _ => eprintln!("Please specify a part (part1 | part2)"),
     }
 }
+
+pub fn has_flag(flag: &str) -> bool {
+    std::env::args().skip(2).any(|arg| arg == flag)
+}
+
+// The argument following `flag`, if it was given
+pub fn flag_value(flag: &str) -> Option<String> {
+    std::env::args()
+        .skip(2)
+        .skip_while(|arg| arg != flag)
+        .nth(1)
+}
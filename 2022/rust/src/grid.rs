@@ -23,3 +23,104 @@ pub fn taxicab_distance<T: Integer + Copy>(
     std::cmp::max(x1, x2) - std::cmp::min(x1, x2) + std::cmp::max(y1, y2) - std::cmp::min(y1, y2)
 }
 
+// For every value, the index of the closest preceding value that is at least
+// as large. Keeps a stack of candidates in decreasing order, so it's O(n).
+pub fn previous_at_least<T: Ord>(values: impl IntoIterator<Item = T>) -> Vec<Option<usize>> {
+    let mut stack: Vec<(usize, T)> = vec![];
+    let mut nearest = vec![];
+    for (i, value) in values.into_iter().enumerate() {
+        while stack.last().is_some_and(|(_, top)| *top < value) {
+            stack.pop();
+        }
+        nearest.push(stack.last().map(|&(j, _)| j));
+        stack.push((i, value));
+    }
+    nearest
+}
+
+// Position of the closest value at least as large in each direction: rows
+// for up and down, columns for left and right.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Nearest {
+    pub up: Option<usize>,
+    pub right: Option<usize>,
+    pub down: Option<usize>,
+    pub left: Option<usize>,
+}
+
+pub fn nearest_at_least<T: Ord>(grid: &[Vec<T>]) -> Vec<Vec<Nearest>> {
+    let (height, width) = (grid.len(), grid.first().map_or(0, |row| row.len()));
+    let mut nearest = vec![vec![Nearest::default(); width]; height];
+    let flip = |n: usize, len: usize| len - 1 - n;
+    for r in 0..height {
+        let row = &grid[r];
+        for (c, left) in previous_at_least(row.iter()).into_iter().enumerate() {
+            nearest[r][c].left = left;
+        }
+        for (c, right) in previous_at_least(row.iter().rev()).into_iter().enumerate() {
+            nearest[r][flip(c, width)].right = right.map(|n| flip(n, width));
+        }
+    }
+    for c in 0..width {
+        let column = || (0..height).map(|r| &grid[r][c]);
+        for (r, up) in previous_at_least(column()).into_iter().enumerate() {
+            nearest[r][c].up = up;
+        }
+        for (r, down) in previous_at_least(column().rev()).into_iter().enumerate() {
+            nearest[flip(r, height)][c].down = down.map(|n| flip(n, height));
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_previous_at_least() {
+        // Equal values count as at least as large
+        assert_eq!(
+            previous_at_least([3, 1, 3, 2, 5]),
+            vec![None, Some(0), Some(0), Some(2), None]
+        );
+        assert_eq!(
+            previous_at_least(["b", "a", "b", "c", "a"]),
+            vec![None, Some(0), Some(0), None, Some(3)]
+        );
+        assert!(previous_at_least(Vec::<u8>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_at_least() {
+        let grid = vec![vec![1, 2, 1], vec![2, 2, 0], vec![1, 0, 3]];
+        let nearest = nearest_at_least(&grid);
+        assert_eq!(
+            nearest[1][1],
+            Nearest {
+                up: Some(0),
+                right: None,
+                down: None,
+                left: Some(0),
+            }
+        );
+        assert_eq!(
+            nearest[0][0],
+            Nearest {
+                up: None,
+                right: Some(1),
+                down: Some(1),
+                left: None,
+            }
+        );
+        assert_eq!(
+            nearest[2][1],
+            Nearest {
+                up: Some(1),
+                right: Some(2),
+                down: None,
+                left: Some(0),
+            }
+        );
+    }
+}
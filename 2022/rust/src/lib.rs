@@ -1,3 +1,4 @@
 pub mod cli;
 pub mod grid;
 pub mod io;
+pub mod voxel;
@@ -0,0 +1,262 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use itertools::{iproduct, Itertools, MinMaxResult};
+
+pub type Voxel = (isize, isize, isize);
+
+// Unit normals of the six faces of a voxel
+const FACES: [Voxel; 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    // Voxels sharing a face
+    Six,
+    // Voxels sharing a face or an edge
+    Eighteen,
+    // Voxels sharing a face, an edge or a vertex
+    TwentySix,
+}
+
+impl Neighbourhood {
+    pub fn offsets(self) -> impl Iterator<Item = Voxel> {
+        let max_distance = match self {
+            Neighbourhood::Six => 1,
+            Neighbourhood::Eighteen => 2,
+            Neighbourhood::TwentySix => 3,
+        };
+        iproduct!(-1..=1, -1..=1, -1..=1).filter(move |&(x, y, z): &Voxel| {
+            let distance = x.abs() + y.abs() + z.abs();
+            distance > 0 && distance <= max_distance
+        })
+    }
+
+    pub fn neighbours(self, (x, y, z): Voxel) -> impl Iterator<Item = Voxel> {
+        self.offsets()
+            .map(move |(dx, dy, dz)| (x + dx, y + dy, z + dz))
+    }
+}
+
+// A dense grid of solid voxels. The bounding box always leaves one layer of
+// empty voxels around the solid ones, so the exterior is connected.
+#[derive(Clone, Debug)]
+pub struct VoxelGrid {
+    origin: Voxel,
+    size: (usize, usize, usize),
+    cells: Vec<bool>,
+}
+
+impl FromIterator<Voxel> for VoxelGrid {
+    fn from_iter<I: IntoIterator<Item = Voxel>>(iter: I) -> Self {
+        let voxels = iter.into_iter().collect_vec();
+        let axis_bounds = |axis: fn(&Voxel) -> isize| match voxels.iter().map(axis).minmax() {
+            MinMaxResult::NoElements => (0, 0),
+            MinMaxResult::OneElement(n) => (n - 1, n + 1),
+            MinMaxResult::MinMax(min, max) => (min - 1, max + 1),
+        };
+        let (min_x, max_x) = axis_bounds(|v| v.0);
+        let (min_y, max_y) = axis_bounds(|v| v.1);
+        let (min_z, max_z) = axis_bounds(|v| v.2);
+        let size = (
+            (max_x - min_x + 1) as usize,
+            (max_y - min_y + 1) as usize,
+            (max_z - min_z + 1) as usize,
+        );
+        let mut grid = Self {
+            origin: (min_x, min_y, min_z),
+            size,
+            cells: vec![false; size.0 * size.1 * size.2],
+        };
+        for voxel in voxels {
+            let i = grid.index(voxel).unwrap();
+            grid.cells[i] = true;
+        }
+        grid
+    }
+}
+
+impl VoxelGrid {
+    fn index(&self, (x, y, z): Voxel) -> Option<usize> {
+        let (x, y, z) = (x - self.origin.0, y - self.origin.1, z - self.origin.2);
+        let (w, h, d) = self.size;
+        if x < 0 || y < 0 || z < 0 || x as usize >= w || y as usize >= h || z as usize >= d {
+            return None;
+        }
+        Some((z as usize * h + y as usize) * w + x as usize)
+    }
+
+    fn voxel(&self, index: usize) -> Voxel {
+        let (w, h, _) = self.size;
+        (
+            self.origin.0 + (index % w) as isize,
+            self.origin.1 + (index / w % h) as isize,
+            self.origin.2 + (index / (w * h)) as isize,
+        )
+    }
+
+    pub fn contains(&self, voxel: Voxel) -> bool {
+        self.index(voxel).is_some_and(|i| self.cells[i])
+    }
+
+    pub fn voxels(&self) -> impl Iterator<Item = Voxel> + '_ {
+        self.cells
+            .iter()
+            .positions(|&solid| solid)
+            .map(|i| self.voxel(i))
+    }
+
+    // Empty voxels reachable from outside the grid, moving across faces
+    pub fn exterior(&self) -> Vec<bool> {
+        let mut exterior = vec![false; self.cells.len()];
+        let mut queue = VecDeque::from([self.origin]);
+        exterior[0] = true;
+        while let Some(voxel) = queue.pop_front() {
+            for neighbour in Neighbourhood::Six.neighbours(voxel) {
+                match self.index(neighbour) {
+                    Some(i) if !self.cells[i] && !exterior[i] => {
+                        exterior[i] = true;
+                        queue.push_back(neighbour);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        exterior
+    }
+
+    pub fn components(&self, neighbourhood: Neighbourhood) -> Vec<Vec<Voxel>> {
+        let mut labels: Vec<Option<usize>> = vec![None; self.cells.len()];
+        let mut components = vec![];
+        for start in self.cells.iter().positions(|&solid| solid) {
+            if labels[start].is_some() {
+                continue;
+            }
+            let label = components.len();
+            let mut component = vec![];
+            let mut queue = VecDeque::from([start]);
+            labels[start] = Some(label);
+            while let Some(i) = queue.pop_front() {
+                let voxel = self.voxel(i);
+                component.push(voxel);
+                for neighbour in neighbourhood.neighbours(voxel) {
+                    match self.index(neighbour) {
+                        Some(j) if self.cells[j] && labels[j].is_none() => {
+                            labels[j] = Some(label);
+                            queue.push_back(j);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    // Faces of solid voxels not covered by another solid voxel, as
+    // (voxel, face normal) pairs. With `exterior_only` faces looking into
+    // trapped air pockets are skipped.
+    pub fn faces(&self, exterior_only: bool) -> impl Iterator<Item = (Voxel, Voxel)> + '_ {
+        let exterior = if exterior_only {
+            Some(self.exterior())
+        } else {
+            None
+        };
+        self.voxels()
+            .cartesian_product(FACES)
+            .filter(move |&((x, y, z), (dx, dy, dz))| {
+                let neighbour = (x + dx, y + dy, z + dz);
+                match (&exterior, self.index(neighbour)) {
+                    (_, None) => true,
+                    (None, Some(i)) => !self.cells[i],
+                    (Some(exterior), Some(i)) => exterior[i],
+                }
+            })
+    }
+
+    pub fn volume(&self) -> usize {
+        self.cells.iter().filter(|&&solid| solid).count()
+    }
+
+    // Volume of the solid voxels plus any air pockets trapped inside them
+    pub fn enclosed_volume(&self) -> usize {
+        self.exterior().iter().filter(|&&outside| !outside).count()
+    }
+
+    pub fn surface_area(&self) -> usize {
+        self.faces(false).count()
+    }
+
+    pub fn exterior_area(&self) -> usize {
+        self.faces(true).count()
+    }
+
+    // Wavefront OBJ mesh with one quad per visible face, vertices shared
+    pub fn to_obj(&self, exterior_only: bool) -> String {
+        let mut vertices: HashMap<Voxel, usize> = HashMap::new();
+        let mut vertex_lines = String::new();
+        let mut face_lines = String::new();
+        for (voxel, normal) in self.faces(exterior_only) {
+            let indices = face_corners(voxel, normal).map(|corner| {
+                let next_index = vertices.len() + 1;
+                *vertices.entry(corner).or_insert_with(|| {
+                    writeln!(vertex_lines, "v {} {} {}", corner.0, corner.1, corner.2).unwrap();
+                    next_index
+                })
+            });
+            writeln!(face_lines, "f {}", indices.iter().format(" ")).unwrap();
+        }
+        vertex_lines + &face_lines
+    }
+}
+
+// Corners of a voxel face, counter-clockwise when seen from outside
+fn face_corners(voxel: Voxel, normal: Voxel) -> [Voxel; 4] {
+    let origin = [voxel.0, voxel.1, voxel.2];
+    let normal = [normal.0, normal.1, normal.2];
+    let axis = normal.iter().position(|&n| n != 0).unwrap();
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+    if normal[axis] < 0 {
+        corners.reverse();
+    }
+    corners.map(|(du, dv)| {
+        let mut corner = origin;
+        corner[axis] += normal[axis].max(0);
+        corner[u] += du;
+        corner[v] += dv;
+        (corner[0], corner[1], corner[2])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hollow_cube() {
+        let shell: VoxelGrid = iproduct!(0..3, 0..3, 0..3)
+            .filter(|&v| v != (1, 1, 1))
+            .collect();
+        assert_eq!(shell.volume(), 26);
+        assert_eq!(shell.enclosed_volume(), 27);
+        assert_eq!(shell.exterior_area(), 54);
+        assert_eq!(shell.surface_area(), 60);
+        assert_eq!(shell.components(Neighbourhood::Six).len(), 1);
+    }
+
+    #[test]
+    fn test_components_by_neighbourhood() {
+        let diagonal: VoxelGrid = vec![(0, 0, 0), (1, 1, 0), (2, 2, 1)].into_iter().collect();
+        assert_eq!(diagonal.components(Neighbourhood::Six).len(), 3);
+        assert_eq!(diagonal.components(Neighbourhood::Eighteen).len(), 2);
+        assert_eq!(diagonal.components(Neighbourhood::TwentySix).len(), 1);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+// A point or displacement on the plane. y grows downwards, like rows on a
+// screen, so north is (0, -1) and turning right maps (x, y) to (-y, x).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vec2 {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2::new(0, 0);
+    pub const NORTH: Vec2 = Vec2::new(0, -1);
+    pub const EAST: Vec2 = Vec2::new(1, 0);
+    pub const SOUTH: Vec2 = Vec2::new(0, 1);
+    pub const WEST: Vec2 = Vec2::new(-1, 0);
+
+    pub const fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    pub fn manhattan(self) -> isize {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn manhattan_distance(self, other: Vec2) -> isize {
+        (self - other).manhattan()
+    }
+
+    // Chebyshev norm: how many king moves away from the origin
+    pub fn chebyshev(self) -> isize {
+        self.x.abs().max(self.y.abs())
+    }
+
+    pub fn signum(self) -> Vec2 {
+        Vec2::new(self.x.signum(), self.y.signum())
+    }
+
+    pub fn turn_right(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    pub fn turn_left(self) -> Vec2 {
+        Vec2::new(self.y, -self.x)
+    }
+
+    // Rotates by 90 degrees `quarter_turns` times, clockwise when positive
+    pub fn rotate(self, quarter_turns: isize) -> Vec2 {
+        match quarter_turns.rem_euclid(4) {
+            0 => self,
+            1 => self.turn_right(),
+            2 => -self,
+            _ => self.turn_left(),
+        }
+    }
+}
+
+impl fmt::Display for Vec2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<isize> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, k: isize) -> Vec2 {
+        Vec2::new(self.x * k, self.y * k)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotations() {
+        assert_eq!(Vec2::EAST.turn_right(), Vec2::SOUTH);
+        assert_eq!(Vec2::EAST.turn_left(), Vec2::NORTH);
+        let waypoint = Vec2::new(10, -4);
+        assert_eq!(waypoint.rotate(1), Vec2::new(4, 10));
+        assert_eq!(waypoint.rotate(-1), waypoint.rotate(3));
+        assert_eq!(waypoint.rotate(2), -waypoint);
+        assert_eq!(waypoint.rotate(-8), waypoint);
+    }
+
+    #[test]
+    fn test_norms() {
+        let v = Vec2::new(17, -8);
+        assert_eq!(v.manhattan(), 25);
+        assert_eq!(v.chebyshev(), 17);
+        assert_eq!(v.signum(), Vec2::new(1, -1));
+        assert_eq!(v.manhattan_distance(Vec2::new(7, 2)), 20);
+    }
+}